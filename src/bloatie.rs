@@ -112,9 +112,7 @@ impl Bloatie {
             Some(Color::White),
         );
 
-        let space = iter::repeat(' ')
-            .take(dialogue.len() + 2)
-            .collect::<String>();
+        let space = " ".repeat(dialogue.len() + 2);
 
         viewport.draw_widget(
             &Text::new(format!("{}", space), Some(Color::White), None),
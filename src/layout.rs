@@ -0,0 +1,420 @@
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tinybit::{ScreenPos, Viewport};
+
+use crate::settings::Widget;
+
+/// A part of the screen handed to a widget or region.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn pos(&self) -> ScreenPos {
+        ScreenPos::new(self.x, self.y)
+    }
+}
+
+/// Size of a row or column: `12` cells, `"30%"` of the screen or
+/// `"2fr"`, a share of whatever the others leave over.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "TrackSpec")]
+pub enum Track {
+    Cells(u16),
+    Percent(u16),
+    Flex(u16),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TrackSpec {
+    Cells(u16),
+    Text(String),
+}
+
+impl TryFrom<TrackSpec> for Track {
+    type Error = String;
+
+    fn try_from(spec: TrackSpec) -> Result<Self, Self::Error> {
+        let text = match spec {
+            TrackSpec::Cells(n) => return Ok(Track::Cells(n)),
+            TrackSpec::Text(text) => text,
+        };
+        let text = text.trim();
+        let number = |n: &str| match n.trim() {
+            "" => Ok(1),
+            n => n
+                .parse::<u16>()
+                .map_err(|_| format!("invalid size {:?}", text)),
+        };
+
+        if let Some(n) = text.strip_suffix('%') {
+            number(n).map(Track::Percent)
+        } else if let Some(n) = text.strip_suffix("fr") {
+            number(n).map(Track::Flex)
+        } else {
+            number(text).map(Track::Cells)
+        }
+    }
+}
+
+/// Offsets and sizes of `tracks` laid out along `total` cells,
+/// `gap` cells apart.
+pub fn split(tracks: &[Track], total: u16, gap: u16) -> Vec<(u16, u16)> {
+    let gaps = gap.saturating_mul(tracks.len().saturating_sub(1) as u16);
+    let available = total.saturating_sub(gaps);
+
+    let mut sizes = tracks
+        .iter()
+        .map(|track| match *track {
+            Track::Cells(n) => n,
+            Track::Percent(p) => (available as u32 * p.min(100) as u32 / 100) as u16,
+            Track::Flex(_) => 0,
+        })
+        .collect::<Vec<_>>();
+
+    // Flex tracks share what is left, rounding leftovers go to the last ones
+    let used = sizes
+        .iter()
+        .fold(0_u16, |sum, &size| sum.saturating_add(size));
+    let remaining = available.saturating_sub(used) as u32;
+    let weights = tracks
+        .iter()
+        .map(|track| match *track {
+            Track::Flex(w) => w as u32,
+            _ => 0,
+        })
+        .sum::<u32>();
+
+    let mut leftover = remaining;
+    for (size, track) in sizes.iter_mut().zip(tracks) {
+        if let Track::Flex(w) = *track {
+            *size = (remaining * w as u32).checked_div(weights).unwrap_or(0) as u16;
+            leftover -= *size as u32;
+        }
+    }
+    for (size, track) in sizes.iter_mut().zip(tracks).rev() {
+        if leftover == 0 {
+            break;
+        }
+        if matches!(track, Track::Flex(w) if *w > 0) {
+            *size += 1;
+            leftover -= 1;
+        }
+    }
+
+    let mut offset = 0_u16;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let start = offset.min(total);
+            let size = size.min(total - start);
+            offset = start.saturating_add(size).saturating_add(gap);
+            (start, size)
+        })
+        .collect()
+}
+
+/// Which end of a region widgets are stacked from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// A named area of the grid, spanning one or more cells.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Region {
+    pub name: String,
+    #[serde(default)]
+    row: usize,
+    #[serde(default)]
+    column: usize,
+    #[serde(default = "one")]
+    row_span: usize,
+    #[serde(default = "one")]
+    column_span: usize,
+    #[serde(default)]
+    pub anchor: Anchor,
+}
+
+impl Region {
+    fn new(name: &str, row: usize, column: usize, anchor: Anchor) -> Self {
+        Self {
+            name: name.to_string(),
+            row,
+            column,
+            row_span: 1,
+            column_span: 1,
+            anchor,
+        }
+    }
+}
+
+fn one() -> usize {
+    1
+}
+
+/// Rows and columns splitting up the screen, with widgets placed into
+/// the named regions on top of them.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Layout {
+    rows: Vec<Track>,
+    columns: Vec<Track>,
+    #[serde(default)]
+    row_gap: u16,
+    #[serde(default)]
+    column_gap: u16,
+    pub regions: Vec<Region>,
+}
+
+impl Layout {
+    /// Fail early on regions that reach outside the grid.
+    pub fn validate(&self) -> Result<()> {
+        for region in &self.regions {
+            if region.row_span == 0
+                || region.column_span == 0
+                || region.row + region.row_span > self.rows.len()
+                || region.column + region.column_span > self.columns.len()
+            {
+                return Err(anyhow!(
+                    "region {:?} doesn't fit the {}x{} layout",
+                    region.name,
+                    self.rows.len(),
+                    self.columns.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Index of the region called `name`.
+    pub fn find(&self, name: &str) -> Result<usize> {
+        self.regions
+            .iter()
+            .position(|r| r.name == name)
+            .ok_or_else(|| anyhow!("no region named {:?} in the layout", name))
+    }
+
+    /// Where every region ends up in `screen`.
+    pub fn areas(&self, screen: Rect) -> Vec<Rect> {
+        let rows = split(&self.rows, screen.height, self.row_gap);
+        let columns = split(&self.columns, screen.width, self.column_gap);
+
+        let span = |tracks: &[(u16, u16)], start: usize, len: usize| {
+            let (first, _) = tracks[start];
+            let (last, last_size) = tracks[start + len - 1];
+            (first, last + last_size - first)
+        };
+
+        self.regions
+            .iter()
+            .map(|region| {
+                let (y, height) = span(&rows, region.row, region.row_span);
+                let (x, width) = span(&columns, region.column, region.column_span);
+                Rect::new(screen.x + x, screen.y + y, width, height)
+            })
+            .collect()
+    }
+}
+
+/// The four corners widgets were placed in before layouts, picked with
+/// their `right` and `bottom` flags.
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            rows: vec![Track::Flex(1), Track::Flex(1)],
+            columns: vec![Track::Flex(1), Track::Flex(1)],
+            row_gap: 0,
+            column_gap: 4,
+            regions: vec![
+                Region::new("top_left", 0, 0, Anchor::Top),
+                Region::new("top_right", 0, 1, Anchor::Top),
+                Region::new("bottom_left", 1, 0, Anchor::Bottom),
+                Region::new("bottom_right", 1, 1, Anchor::Bottom),
+            ],
+        }
+    }
+}
+
+/// Region of the default layout for a widget without one.
+pub fn corner(right: bool, bottom: bool) -> &'static str {
+    match (right, bottom) {
+        (false, false) => "top_left",
+        (true, false) => "top_right",
+        (false, true) => "bottom_left",
+        (true, true) => "bottom_right",
+    }
+}
+
+/// Draw `widgets` one below the other in `area`, starting at the end
+/// `anchor` points to.
+pub fn stack(
+    widgets: &mut [&mut dyn Widget],
+    viewport: &mut Viewport,
+    area: Rect,
+    anchor: Anchor,
+    resized: &bool,
+) -> Result<()> {
+    let mut top = area.y;
+    let mut bottom = area.y + area.height;
+
+    for widget in widgets.iter_mut() {
+        let height = widget.vertical_size() as u16;
+        let y = match anchor {
+            Anchor::Top => {
+                top += height;
+                top - height
+            }
+            Anchor::Bottom => {
+                bottom = bottom.saturating_sub(height);
+                bottom
+            }
+        };
+
+        widget.update_and_draw(viewport, Rect::new(area.x, y, area.width, height), resized)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(toml: &str) -> Result<Track, toml::de::Error> {
+        #[derive(Deserialize)]
+        struct Spec {
+            size: Track,
+        }
+        toml::from_str::<Spec>(&format!("size = {}", toml)).map(|spec| spec.size)
+    }
+
+    #[test]
+    fn tracks_parse_cells_percent_and_flex() {
+        assert_eq!(track("12").unwrap(), Track::Cells(12));
+        assert_eq!(track("\"12\"").unwrap(), Track::Cells(12));
+        assert_eq!(track("\"30%\"").unwrap(), Track::Percent(30));
+        assert_eq!(track("\"2fr\"").unwrap(), Track::Flex(2));
+        assert_eq!(track("\" 3 fr \"").unwrap(), Track::Flex(3));
+        assert_eq!(track("\"fr\"").unwrap(), Track::Flex(1));
+    }
+
+    #[test]
+    fn invalid_tracks_are_errors() {
+        for spec in &["\"wide\"", "\"x%\"", "\"-2fr\"", "-1"] {
+            assert!(track(spec).is_err(), "{} parsed", spec);
+        }
+        assert!(track("\"abc\"")
+            .unwrap_err()
+            .to_string()
+            .contains("invalid size"));
+    }
+
+    #[test]
+    fn split_cells_and_percent() {
+        let tracks = [Track::Cells(10), Track::Percent(50)];
+        assert_eq!(split(&tracks, 100, 0), vec![(0, 10), (10, 50)]);
+        // Percent of what is left after the gaps
+        assert_eq!(split(&tracks, 102, 2), vec![(0, 10), (12, 50)]);
+        // Clamped to 100%
+        assert_eq!(split(&[Track::Percent(150)], 40, 0), vec![(0, 40)]);
+    }
+
+    #[test]
+    fn flex_shares_the_rest_and_leftovers_go_last() {
+        let tracks = [Track::Flex(1), Track::Cells(4), Track::Flex(2)];
+        assert_eq!(split(&tracks, 34, 0), vec![(0, 10), (10, 4), (14, 20)]);
+
+        let tracks = [Track::Flex(1), Track::Flex(1), Track::Flex(1)];
+        assert_eq!(split(&tracks, 11, 1), vec![(0, 3), (4, 3), (8, 3)]);
+        assert_eq!(split(&tracks, 10, 0), vec![(0, 3), (3, 3), (6, 4)]);
+        assert_eq!(split(&tracks, 11, 0), vec![(0, 3), (3, 4), (7, 4)]);
+    }
+
+    #[test]
+    fn zero_weights_and_overflow() {
+        assert_eq!(
+            split(&[Track::Flex(0), Track::Flex(0)], 10, 0),
+            vec![(0, 0), (0, 0)]
+        );
+        assert_eq!(
+            split(&[Track::Flex(0), Track::Flex(1)], 10, 0),
+            vec![(0, 0), (0, 10)]
+        );
+
+        // Tracks past the end are cut off, never past `total`
+        let tracks = [Track::Cells(8), Track::Cells(8)];
+        assert_eq!(split(&tracks, 10, 1), vec![(0, 8), (9, 1)]);
+        assert_eq!(
+            split(&[Track::Cells(u16::MAX); 3], 10, 0),
+            vec![(0, 10), (10, 0), (10, 0)]
+        );
+        assert_eq!(split(&[], 10, 2), vec![]);
+    }
+
+    #[test]
+    fn default_layout_is_the_four_corners() {
+        let layout = Layout::default();
+        layout.validate().unwrap();
+
+        let areas = layout.areas(Rect::new(0, 1, 20, 10));
+        assert_eq!(
+            areas,
+            vec![
+                Rect::new(0, 1, 8, 5),
+                Rect::new(12, 1, 8, 5),
+                Rect::new(0, 6, 8, 5),
+                Rect::new(12, 6, 8, 5),
+            ]
+        );
+        assert_eq!(layout.find(corner(true, true)).unwrap(), 3);
+        assert!(layout.find("middle").is_err());
+    }
+
+    #[test]
+    fn regions_span_tracks_and_must_fit() {
+        let mut layout: Layout = toml::from_str(
+            r#"
+            rows = [2, "1fr"]
+            columns = ["1fr", "1fr"]
+            column_gap = 2
+
+            [[regions]]
+            name = "header"
+            column_span = 2
+
+            [[regions]]
+            name = "side"
+            row = 1
+            column = 1
+            "#,
+        )
+        .unwrap();
+        layout.validate().unwrap();
+        assert_eq!(
+            layout.areas(Rect::new(0, 0, 22, 10)),
+            vec![Rect::new(0, 0, 22, 2), Rect::new(12, 2, 10, 8)]
+        );
+
+        layout.regions[1].column_span = 2;
+        assert!(layout.validate().is_err());
+    }
+}
@@ -3,18 +3,24 @@
 //      ▀█▀█▀ █▄█ █░▀█ █░█ ░█░
 // For your terminal monitoring needs
 //
+use crate::layout::{Layout, Rect};
+use crate::scheduler::Scheduler;
 use crate::settings::meter_theme::MeterTheme;
-use crate::settings::Widget;
+use crate::settings::{Inline, Page, Widget};
 use anyhow::Result;
 use chrono::{Local, Timelike};
 use rand::Rng;
 use std::env;
+use std::time::{Duration, Instant};
 
 use tinybit::events::{events, Event, EventModel, KeyCode, KeyEvent, KeyModifiers};
 use tinybit::render::{Renderer, StdoutTarget};
 use tinybit::{term_size, Color, ScreenPos, ScreenSize, Viewport};
 
 mod bloatie;
+mod layout;
+mod popup;
+mod scheduler;
 mod settings;
 
 pub use bloatie::{Bloatie, BloatieAnimation};
@@ -31,31 +37,57 @@ fn main() -> Result<()> {
         Some(path) => settings::load_at_path(&path)?,
     };
 
-    let mut positions: [Vec<&mut dyn Widget>; 4] = [vec![], vec![], vec![], vec![]];
-
-    // TODO: Should probably insert bottom aligned Widgets at index 0
-    // to make making designing layouts in config more intuitive.
-    for w in conf.widgets.iter_mut() {
-        match w {
-            Element::Meter(m) => {
-                m.init()?;
-                m.theme.resize((width / 2 - 2) as u8);
-                m.theme.init();
-                positions[pos_index(m.right, m.bottom)].push(m);
-            }
-            Element::Indicator(i) => {
-                i.init()?;
-                positions[pos_index(i.right, i.bottom)].push(i);
-            }
-            Element::Separator(s) => {
-                s.theme.init();
-                positions[pos_index(s.right, s.bottom)].push(s);
-            }
+    let single_row = conf.settings.single_row;
+    let rotate = conf.settings.rotate.map(Duration::from_secs);
+
+    let mut pages: Vec<Screen> = vec![];
+    let mut scheduler = Scheduler::new();
+
+    for Page {
+        title,
+        widgets,
+        layout,
+    } in conf.pages.iter_mut()
+    {
+        // A status bar keeps the widgets in one list, in config order
+        let mut regions: Vec<Vec<&mut dyn Widget>> = if single_row {
+            vec![vec![]]
+        } else {
+            layout.regions.iter().map(|_| vec![]).collect()
+        };
+
+        // TODO: Should probably insert bottom aligned Widgets at index 0
+        // to make making designing layouts in config more intuitive.
+        for w in widgets.iter_mut() {
+            w.init(&mut scheduler)?;
+            let widget = w.widget_mut();
+            let region = match widget.region() {
+                _ if single_row => 0,
+                Some(region) => layout.find(region)?,
+                None => layout.find(layout::corner(widget.is_right(), widget.is_bottom()))?,
+            };
+            regions[region].push(widget);
         }
+
+        pages.push(Screen {
+            title: title.as_deref(),
+            layout,
+            regions,
+        });
     }
 
-    let mut bloatie = if conf.settings.bloatie {
-        let mut bloat = Bloatie::new(width - 6, 0);
+    scheduler.start();
+
+    // The page line takes the top row when there is more than one page
+    let total = pages.len();
+    let titles = pages.iter().map(|p| p.title).collect::<Vec<_>>();
+    let top = (total > 1 && !single_row) as u16;
+    let mut page = 0;
+    let mut shown = Instant::now();
+
+    // No room for the character on a single line
+    let mut bloatie = if conf.settings.bloatie && !single_row {
+        let mut bloat = Bloatie::new(width - 6, top);
         bloat.speak("Hello!!");
         Some(bloat)
     } else {
@@ -67,43 +99,78 @@ fn main() -> Result<()> {
     let sleepy_time = 0..7;
 
     let mut timer = std::time::Instant::now();
-    let mut resized = false;
+    // Widgets size themselves to their area on the first frame
+    let mut resized = true;
+
+    // Keyboard selected widget and whether its detail popup is open
+    let mut selected: Option<usize> = None;
+    let mut show_details = false;
+    let mut next_page: Option<usize> = None;
 
     for event in events(EventModel::Fps(fps)) {
-        match event {
-            Event::Tick => {
-                (0..4).try_for_each(|n| {
-                    let right = n == 1 || n == 3;
-                    let bottom = n == 2 || n == 3;
+        // Wall displays flip through the pages on their own
+        if total > 1 && rotate.is_some_and(|every| shown.elapsed() >= every) {
+            next_page = Some((page + 1) % total);
+        }
+
+        // A new page starts out unselected and sizes its widgets anew
+        if let Some(next) = next_page.take() {
+            select(&mut pages[page].regions, selected, 0);
+            page = next;
+            selected = None;
+            show_details = false;
+            shown = Instant::now();
+            resized = true;
+        }
 
-                    let increment = if bottom { -1 } else { 1 };
+        // Hidden pages keep taking readings, only the shown one is drawn
+        if let Event::Tick = event {
+            for widget in pages
+                .iter_mut()
+                .flat_map(|p| p.regions.iter_mut().flatten())
+            {
+                widget.update()?;
+            }
+        }
 
-                    // TODO: Offsets are not great, but it works, figure out why
-                    // at some point
-                    let vertical_pos = if bottom { height as i16 - 1 } else { 0 };
-                    let horizontal_pos = if right { width / 2 + 2 } else { 0 };
+        let screen = &mut pages[page];
 
-                    let mut i = 0;
-                    if right && !bottom && bloatie.is_some() {
-                        i = 3;
+        match event {
+            Event::Tick => {
+                if single_row {
+                    let mut line = Inline::new(ScreenPos::zero(), width).spaced(2);
+                    if total > 1 {
+                        line.text(&mut viewport, &tab(page, screen.title), TAB_SHOWN);
+                    }
+                    for widget in screen.regions.iter_mut().flatten() {
+                        line.widget(|pos, width| widget.draw_inline(&mut viewport, pos, width))?;
+                    }
+                } else {
+                    if top > 0 {
+                        draw_tabs(&mut viewport, &titles, page, width);
                     }
 
-                    for widget in positions[n].iter_mut() {
-                        widget.update_and_draw(
-                            &mut viewport,
-                            &mut ScreenPos::new(horizontal_pos, (vertical_pos + i) as u16),
-                            &resized,
-                        )?;
-
-                        // If single-line or not, make prettier?
-                        if widget.vertical_size() == 1 {
-                            i += increment;
+                    let layout = screen.layout;
+                    let areas = layout.areas(Rect::new(0, top, width, height - top));
+                    for ((region, area), widgets) in layout
+                        .regions
+                        .iter()
+                        .zip(areas)
+                        .zip(screen.regions.iter_mut())
+                    {
+                        // Leave room for the character in the top right corner
+                        let area = if bloatie.is_some()
+                            && area.y == top
+                            && area.x + area.width >= width
+                        {
+                            Rect::new(area.x, top + 3, area.width, area.height.saturating_sub(3))
                         } else {
-                            i += increment * 2;
-                        }
+                            area
+                        };
+
+                        layout::stack(widgets, &mut viewport, area, region.anchor, &resized)?;
                     }
-                    Ok::<_, anyhow::Error>(())
-                })?;
+                }
                 resized = false;
 
                 // Character
@@ -129,12 +196,38 @@ fn main() -> Result<()> {
                     }
                     b.update(&mut viewport);
                 }
+
+                if show_details {
+                    if let Some(popup) = selected
+                        .and_then(|n| leaves(&mut screen.regions).into_iter().nth(n))
+                        .and_then(|w| w.details())
+                    {
+                        popup.draw(&mut viewport);
+                    }
+                }
+
                 renderer.render(&mut viewport);
             }
 
             Event::Key(KeyEvent { code, modifiers }) => match code {
                 KeyCode::Enter | KeyCode::Char('q') => return Ok(()),
                 KeyCode::Char('c') if modifiers == KeyModifiers::CONTROL => return Ok(()),
+                KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < total => {
+                    next_page = Some(c as usize - '1' as usize);
+                }
+                KeyCode::Char(']') if total > 1 => next_page = Some((page + 1) % total),
+                KeyCode::Char('[') if total > 1 => next_page = Some((page + total - 1) % total),
+                KeyCode::Tab | KeyCode::Down | KeyCode::Char('j') => {
+                    selected = select(&mut screen.regions, selected, 1);
+                }
+                KeyCode::BackTab | KeyCode::Up | KeyCode::Char('k') => {
+                    selected = select(&mut screen.regions, selected, -1);
+                }
+                KeyCode::Char('d') | KeyCode::Char(' ') => {
+                    show_details = selected.is_some() && !show_details;
+                }
+                KeyCode::Esc if show_details => show_details = false,
+                KeyCode::Esc => selected = select(&mut screen.regions, selected, 0),
                 _ => {}
             },
 
@@ -147,7 +240,7 @@ fn main() -> Result<()> {
                 resized = true;
 
                 if let Some(b) = &mut bloatie {
-                    b.relocate(width - 6, 0);
+                    b.relocate(width - 6, top);
                 }
             }
         }
@@ -166,6 +259,84 @@ fn bg_color() -> Option<Color> {
     Some(Color::DarkGreen)
 }
 
-fn pos_index(right: bool, bottom: bool) -> usize {
-    right as usize | (bottom as usize) << 1
+/// A page as drawn, its layout and the widgets in every region of it.
+struct Screen<'a> {
+    title: Option<&'a str>,
+    layout: &'a Layout,
+    regions: Vec<Vec<&'a mut dyn Widget>>,
+}
+
+/// Colors of the page shown and the others on the page line.
+const TAB_SHOWN: (Option<Color>, Option<Color>) = (Some(Color::Black), Some(Color::White));
+const TAB: (Option<Color>, Option<Color>) = (Some(Color::Grey), None);
+
+/// Name of page `n` on the page line.
+fn tab(n: usize, title: Option<&str>) -> String {
+    match title {
+        Some(title) => format!(" {} {} ", n + 1, title),
+        None => format!(" {} ", n + 1),
+    }
+}
+
+/// The page line, every page by number and title.
+fn draw_tabs(viewport: &mut Viewport, titles: &[Option<&str>], page: usize, width: u16) {
+    let mut line = Inline::new(ScreenPos::zero(), width);
+    for (n, title) in titles.iter().enumerate() {
+        let colors = if n == page { TAB_SHOWN } else { TAB };
+        line.text(viewport, &tab(n, *title), colors);
+    }
+}
+
+/// Move the selection `step` inspectable widgets forward or back,
+/// a step of 0 clears it.
+fn select(
+    regions: &mut [Vec<&mut dyn Widget>],
+    current: Option<usize>,
+    step: isize,
+) -> Option<usize> {
+    let mut widgets = leaves(regions);
+    let candidates = widgets
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| w.details().is_some())
+        .map(|(n, _)| n)
+        .collect::<Vec<_>>();
+
+    let index = current.and_then(|c| candidates.iter().position(|&n| n == c));
+
+    let next = match (index, step) {
+        (_, 0) => None,
+        (None, s) if s > 0 => candidates.first().copied(),
+        (None, _) => candidates.last().copied(),
+        (Some(i), s) => {
+            let len = candidates.len() as isize;
+            Some(candidates[(i as isize + s).rem_euclid(len) as usize])
+        }
+    };
+
+    for (n, widget) in widgets.iter_mut().enumerate() {
+        widget.set_selected(Some(n) == next);
+    }
+
+    next
+}
+
+/// Every widget that can be selected, those inside containers taking
+/// the place of their container.
+fn leaves<'a>(regions: &'a mut [Vec<&mut dyn Widget>]) -> Vec<&'a mut dyn Widget> {
+    fn collect<'a>(widget: &'a mut dyn Widget, leaves: &mut Vec<&'a mut dyn Widget>) {
+        if widget.children().is_empty() {
+            leaves.push(widget);
+        } else {
+            for child in widget.children() {
+                collect(child, leaves);
+            }
+        }
+    }
+
+    let mut leaves = vec![];
+    for widget in regions.iter_mut().flatten() {
+        collect(&mut **widget, &mut leaves);
+    }
+    leaves
 }
@@ -0,0 +1,63 @@
+use tinybit::widgets::Text;
+use tinybit::{Color, ScreenPos, Viewport};
+
+/// A bordered box drawn centered on top of everything else.
+pub struct Popup {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+impl Popup {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+        }
+    }
+
+    pub fn draw(&self, viewport: &mut Viewport) {
+        let longest = self
+            .lines
+            .iter()
+            .chain(std::iter::once(&self.title))
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+
+        let width = (longest + 4).min(viewport.size.width);
+        let height = (self.lines.len() as u16 + 2).min(viewport.size.height);
+        if width < 4 || height < 2 {
+            return;
+        }
+
+        let x = (viewport.size.width - width) / 2;
+        let y = (viewport.size.height - height) / 2;
+        let inner = (width - 4) as usize;
+
+        let fg = Some(Color::White);
+        let bg = Some(Color::Black);
+
+        let title = truncate(&self.title, inner);
+        let top = format!("┌ {} {}┐", title, "─".repeat(inner - title.chars().count()));
+        viewport.draw_widget(&Text::new(top, fg, bg), ScreenPos::new(x, y));
+
+        for (i, line) in self.lines.iter().take(height as usize - 2).enumerate() {
+            let line = truncate(line, inner);
+            let padding = " ".repeat(inner - line.chars().count());
+            viewport.draw_widget(
+                &Text::new(format!("│ {}{} │", line, padding), fg, bg),
+                ScreenPos::new(x, y + 1 + i as u16),
+            );
+        }
+
+        let bottom = format!("└{}┘", "─".repeat(inner + 2));
+        viewport.draw_widget(
+            &Text::new(bottom, fg, bg),
+            ScreenPos::new(x, y + height - 1),
+        );
+    }
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs widget jobs on their own worker threads so that a slow command
+/// never blocks drawing or key handling.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `task` every `frequency` seconds, publishing each result.
+    pub fn every<T, F>(&mut self, frequency: u64, task: F) -> Latest<T>
+    where
+        T: Send + 'static,
+        F: FnMut() -> T + Send + 'static,
+    {
+        self.push(Some(Duration::from_secs(frequency.max(1))), task)
+    }
+
+    /// Run `task` a single time in the background.
+    pub fn once<T, F>(&mut self, task: F) -> Latest<T>
+    where
+        T: Send + 'static,
+        F: FnMut() -> T + Send + 'static,
+    {
+        self.push(None, task)
+    }
+
+    /// Run a long lived `task` that publishes as often as it likes.
+    pub fn stream<T, F>(&mut self, mut task: F) -> Latest<T>
+    where
+        T: Send + 'static,
+        F: FnMut(&dyn Fn(T)) + Send + 'static,
+    {
+        let latest = Latest::default();
+        let slot = latest.clone();
+
+        self.jobs.push(Job {
+            interval: None,
+            task: Box::new(move || task(&|value| slot.publish(value))),
+        });
+
+        latest
+    }
+
+    /// Spawn a worker thread for every scheduled job.
+    pub fn start(self) {
+        for job in self.jobs {
+            thread::spawn(move || job.run());
+        }
+    }
+
+    fn push<T, F>(&mut self, interval: Option<Duration>, mut task: F) -> Latest<T>
+    where
+        T: Send + 'static,
+        F: FnMut() -> T + Send + 'static,
+    {
+        let latest = Latest::default();
+        let slot = latest.clone();
+
+        self.jobs.push(Job {
+            interval,
+            task: Box::new(move || slot.publish(task())),
+        });
+
+        latest
+    }
+}
+
+struct Job {
+    interval: Option<Duration>,
+    task: Box<dyn FnMut() + Send>,
+}
+
+impl Job {
+    fn run(mut self) {
+        loop {
+            let started = Instant::now();
+            (self.task)();
+
+            match self.interval {
+                Some(interval) => thread::sleep(interval.saturating_sub(started.elapsed())),
+                None => return,
+            }
+        }
+    }
+}
+
+//----------------------------------------------------------------------------+
+// Latest                                                                     |
+//----------------------------------------------------------------------------+
+
+/// Shared slot holding the most recent, not yet consumed, result of a job
+/// along with when it was published.
+pub struct Latest<T>(Arc<Mutex<Option<(T, Instant)>>>);
+
+impl<T> Latest<T> {
+    /// Take the newest result, if one arrived since the last call.
+    pub fn take(&self) -> Option<T> {
+        self.take_stamped().map(|(value, _)| value)
+    }
+
+    /// Like `take`, but also says when the job produced the result, which
+    /// can be well before the draw pass gets to it.
+    pub fn take_stamped(&self) -> Option<(T, Instant)> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+
+    fn publish(&self, value: T) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some((value, Instant::now()));
+    }
+}
+
+impl<T> Clone for Latest<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Default for Latest<T> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(None)))
+    }
+}
+
+impl<T> fmt::Debug for Latest<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Latest")
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use tinybit::Color;
+
+use super::parse_ansi;
+
+/// Foreground and background of a widget, along with the colors it
+/// takes on while stale or failing. Flattened into the themes of the
+/// widgets that draw a reading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Colors {
+    fg: Option<u8>,
+    bg: Option<u8>,
+    #[serde(default = "super::stale_fg")]
+    stale_fg: Option<u8>,
+    #[serde(default = "super::error_fg")]
+    error_fg: Option<u8>,
+
+    #[serde(default)]
+    pub fg_color: Option<Color>,
+    #[serde(default)]
+    pub bg_color: Option<Color>,
+    #[serde(default)]
+    pub stale_color: Option<Color>,
+    #[serde(default)]
+    pub error_color: Option<Color>,
+}
+
+impl Colors {
+    pub fn new(fg: Option<u8>, bg: Option<u8>) -> Self {
+        Self {
+            fg,
+            bg,
+            stale_fg: super::stale_fg(),
+            error_fg: super::error_fg(),
+            fg_color: None,
+            bg_color: None,
+            stale_color: None,
+            error_color: None,
+        }
+    }
+
+    pub fn init(&mut self) {
+        self.fg_color = parse_ansi(self.fg);
+        self.bg_color = parse_ansi(self.bg);
+        self.stale_color = parse_ansi(self.stale_fg);
+        self.error_color = parse_ansi(self.error_fg);
+    }
+
+    /// Foreground color, dimmed while the reading is stale.
+    pub fn fg(&self, stale: bool) -> Option<Color> {
+        if stale {
+            self.stale_color
+        } else {
+            self.fg_color
+        }
+    }
+
+    /// Text colors for title and reading, inverted while selected.
+    pub fn text_colors(&self, stale: bool, selected: bool) -> (Option<Color>, Option<Color>) {
+        text_colors(self.fg(stale), selected)
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors::new(Some(7), None)
+    }
+}
+
+/// Text in `fg`, the other way around while the widget is selected.
+pub fn text_colors(fg: Option<Color>, selected: bool) -> (Option<Color>, Option<Color>) {
+    if selected {
+        (Some(Color::Black), fg)
+    } else {
+        (fg, None)
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::iter;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use directories_next::BaseDirs;
+use serde::Deserialize;
+
+/// How often a child process is polled while waiting on a timeout.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How many lines of stderr are kept around for the detail popup.
+const STDERR_TAIL: usize = 8;
+
+/// Restart delays for streaming commands that exit, doubling up to the max.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A widget command, either an argv array or a string run through the shell:
+///
+/// ```toml
+/// value_command = ["bash", "~/scripts/cputemp"]
+/// value_command.shell = "free -m | awk '/Mem/ { print $3 }'"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum CommandLine {
+    Argv(Vec<String>),
+    Shell { shell: String },
+}
+
+impl CommandLine {
+    /// Program and arguments, with `~` and `$VAR` expanded in argv mode.
+    pub fn argv(&self, vars: &HashMap<String, String>) -> Vec<String> {
+        match self {
+            CommandLine::Argv(argv) => argv.iter().map(|arg| expand(arg, vars)).collect(),
+            CommandLine::Shell { shell } => {
+                let (sh, flag) = if cfg!(windows) {
+                    ("cmd", "/C")
+                } else {
+                    ("sh", "-c")
+                };
+                vec![sh.to_string(), flag.to_string(), shell.clone()]
+            }
+        }
+    }
+}
+
+impl From<Vec<String>> for CommandLine {
+    fn from(argv: Vec<String>) -> Self {
+        CommandLine::Argv(argv)
+    }
+}
+
+/// Expand a leading `~` and any `$VAR` or `${VAR}`, looking in the
+/// widget's `env` table before the environment wonky was started with.
+/// Unknown variables are left alone so things like awk's `$NF` survive.
+pub fn expand(arg: &str, vars: &HashMap<String, String>) -> String {
+    let lookup = |name: &str| vars.get(name).cloned().or_else(|| env::var(name).ok());
+
+    let mut out = String::with_capacity(arg.len());
+    let mut rest = arg;
+
+    if rest == "~" || rest.starts_with("~/") {
+        if let Some(dirs) = BaseDirs::new() {
+            out.push_str(&dirs.home_dir().to_string_lossy());
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let braced = rest.starts_with("${");
+        let name_start = if braced { 2 } else { 1 };
+        let name_len = rest[name_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len() - name_start);
+        let name = &rest[name_start..name_start + name_len];
+
+        let end = name_start + name_len + braced as usize;
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && (!braced || rest[name_start + name_len..].starts_with('}'));
+
+        match lookup(name).filter(|_| valid) {
+            Some(value) => {
+                out.push_str(&value);
+                rest = &rest[end..];
+            }
+            None => {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Why a widget command did not produce a usable reading.
+#[derive(Debug, Clone)]
+pub enum CommandError {
+    /// The process could not be started at all.
+    Spawn(io::ErrorKind),
+    /// The process exited unsuccessfully, `None` if killed by a signal.
+    Exit(Option<i32>),
+    /// The process did not finish within its timeout and was killed.
+    TimedOut,
+    /// Stdout was not valid UTF-8.
+    Utf8,
+    /// Stdout could not be parsed into the expected value.
+    Parse(String),
+    /// A built-in source could not be read.
+    Source(String),
+    /// `json_path`, `regex` or `field` found nothing in the output.
+    Extract(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Spawn(kind) => write!(f, "spawn: {}", kind),
+            CommandError::Exit(Some(code)) => write!(f, "exit {}", code),
+            CommandError::Exit(None) => f.write_str("killed"),
+            CommandError::TimedOut => f.write_str("timed out"),
+            CommandError::Utf8 => f.write_str("invalid utf-8"),
+            CommandError::Parse(output) => write!(f, "can't parse {:?}", output),
+            CommandError::Source(reason) | CommandError::Extract(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(err: io::Error) -> Self {
+        CommandError::Spawn(err.kind())
+    }
+}
+
+/// What is left of a run besides stdout, shown in the detail popup.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub command: String,
+    pub status: Option<i32>,
+    pub stderr: Vec<String>,
+}
+
+impl Diagnostics {
+    /// Lines describing the last run, for the detail popup.
+    pub fn lines(&self, error: Option<&CommandError>) -> Vec<String> {
+        let status = match self.status {
+            Some(code) => format!("exit {}", code),
+            None if self.command.is_empty() => "not run yet".to_string(),
+            None => "no exit code".to_string(),
+        };
+
+        let mut lines = vec![
+            format!("command: {}", self.command),
+            format!("status:  {}", status),
+        ];
+
+        if let Some(e) = error {
+            lines.push(format!("error:   {}", e));
+        }
+
+        if !self.stderr.is_empty() {
+            lines.push("stderr:".to_string());
+            lines.extend(self.stderr.iter().map(|l| format!("  {}", l)));
+        }
+
+        lines
+    }
+}
+
+/// Trimmed stdout of a run, along with its diagnostics.
+pub type Outcome = (Result<String, CommandError>, Diagnostics);
+
+pub trait CommandExt {
+    fn run(&mut self, timeout: Option<Duration>) -> Outcome;
+
+    /// Spawn the command once and publish every line it prints,
+    /// restarting it with backoff whenever it exits. Never returns.
+    fn stream(&mut self, publish: &dyn Fn(Outcome));
+}
+
+impl CommandExt for Command {
+    fn run(&mut self, timeout: Option<Duration>) -> Outcome {
+        let mut diagnostics = Diagnostics {
+            command: describe(self),
+            ..Diagnostics::default()
+        };
+
+        let stdout = output_timeout(self, timeout).and_then(|output| {
+            diagnostics.status = output.status.code();
+            diagnostics.stderr = tail(&output.stderr);
+
+            if !output.status.success() {
+                return Err(CommandError::Exit(output.status.code()));
+            }
+
+            String::from_utf8(output.stdout)
+                .map(|stdout| stdout.trim().to_string())
+                .map_err(|_| CommandError::Utf8)
+        });
+
+        (stdout, diagnostics)
+    }
+
+    fn stream(&mut self, publish: &dyn Fn(Outcome)) {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let started = Instant::now();
+            let mut diagnostics = Diagnostics {
+                command: describe(self),
+                ..Diagnostics::default()
+            };
+
+            let spawned = self
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn();
+
+            match spawned {
+                Err(e) => publish((Err(e.into()), diagnostics)),
+                Ok(mut child) => {
+                    let stderr = drain_tail(child.stderr.take().expect("stderr is piped"));
+                    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+                    let mut line = Vec::new();
+                    while let Ok(1..) = stdout.read_until(b'\n', &mut line) {
+                        let reading = std::str::from_utf8(&line)
+                            .map(|l| l.trim().to_string())
+                            .map_err(|_| CommandError::Utf8);
+                        publish((reading, diagnostics.clone()));
+                        line.clear();
+                    }
+
+                    let status = child.wait().ok().and_then(|s| s.code());
+                    diagnostics.status = status;
+                    diagnostics.stderr = stderr.join().unwrap_or_default();
+
+                    // A clean exit just gets restarted, only surface failures
+                    if status != Some(0) {
+                        publish((Err(CommandError::Exit(status)), diagnostics));
+                    }
+                }
+            }
+
+            // Only back off further if the process keeps dying quickly
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = MIN_BACKOFF;
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+fn output_timeout(cmd: &mut Command, timeout: Option<Duration>) -> Result<Output, CommandError> {
+    let timeout = match timeout {
+        None => return cmd.output().map_err(Into::into),
+        Some(timeout) => timeout,
+    };
+
+    // A process group of its own, so a timeout takes down everything
+    // it started too, like the rest of a pipeline
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain the pipes on their own threads so a chatty child can't
+    // block on a full pipe while we wait for it.
+    let stdout = drain(child.stdout.take().expect("stdout is piped"));
+    let stderr = drain(child.stderr.take().expect("stderr is piped"));
+
+    let status = wait_timeout(&mut child, timeout)?;
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Program and arguments of `cmd`, quoted. Unlike its `Debug` output
+/// this leaves out env and cwd, which may well hold secrets.
+fn describe(cmd: &Command) -> String {
+    iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| format!("{:?}", arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Like `drain`, but only keeps the last few lines, for long running children.
+fn drain_tail(pipe: impl Read + Send + 'static) -> JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut lines = VecDeque::with_capacity(STDERR_TAIL + 1);
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            lines.push_back(line);
+            if lines.len() > STDERR_TAIL {
+                lines.pop_front();
+            }
+        }
+        lines.into()
+    })
+}
+
+fn tail(stderr: &[u8]) -> Vec<String> {
+    let stderr = String::from_utf8_lossy(stderr);
+    let lines = stderr.lines().collect::<Vec<_>>();
+
+    lines[lines.len().saturating_sub(STDERR_TAIL)..]
+        .iter()
+        .map(|l| l.to_string())
+        .collect()
+}
+
+/// Parse command output, keeping the offending text around on failure.
+pub fn parse<T: FromStr>(output: &str) -> Result<T, CommandError> {
+    output
+        .parse()
+        .map_err(|_| CommandError::Parse(output.to_string()))
+}
+
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Result<ExitStatus, CommandError> {
+    let deadline = Instant::now() + timeout;
+
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    kill(child);
+    let _ = child.wait();
+
+    Err(CommandError::TimedOut)
+}
+
+/// Kill `child` along with its process group, see `output_timeout`.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[test]
+    fn stdout_is_trimmed() {
+        let (stdout, diagnostics) = Command::new("echo").arg(" 42 ").run(None);
+        assert_eq!(stdout.unwrap(), "42");
+        assert_eq!(diagnostics.status, Some(0));
+    }
+
+    #[test]
+    fn exit_status_is_an_error_and_keeps_stderr() {
+        for timeout in [None, Some(Duration::from_secs(5))] {
+            let (stdout, diagnostics) = sh("echo oops >&2; exit 127").run(timeout);
+            assert!(matches!(stdout, Err(CommandError::Exit(Some(127)))));
+            assert_eq!(diagnostics.status, Some(127));
+            assert_eq!(diagnostics.stderr, vec!["oops"]);
+        }
+    }
+
+    #[test]
+    fn diagnostics_leave_out_env_and_cwd() {
+        let (_, diagnostics) = Command::new("echo")
+            .arg("a b")
+            .env("TOKEN", "hunter2")
+            .current_dir("/")
+            .run(None);
+        assert_eq!(diagnostics.command, r#""echo" "a b""#);
+    }
+
+    #[test]
+    fn only_the_tail_of_stderr_is_kept() {
+        let (_, diagnostics) = sh("for i in $(seq 20); do echo $i >&2; done; exit 1").run(None);
+        let tail = (13..=20).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(diagnostics.stderr, tail);
+    }
+
+    #[test]
+    fn timeout_kills_the_whole_pipeline() {
+        let marker = env::temp_dir().join(format!("wonky-timeout-{}", std::process::id()));
+        let script = format!("(sleep 0.5; touch {}) | cat", marker.display());
+
+        let started = Instant::now();
+        let (stdout, _) = sh(&script).run(Some(Duration::from_millis(100)));
+        assert!(matches!(stdout, Err(CommandError::TimedOut)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // Left running, the subshell would have touched the marker by now
+        thread::sleep(Duration::from_secs(1));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn non_utf8_stdout_is_an_error() {
+        let (stdout, _) = sh("printf '\\377'").run(None);
+        assert!(matches!(stdout, Err(CommandError::Utf8)));
+    }
+
+    #[test]
+    fn missing_binary_fails_to_spawn() {
+        let (stdout, diagnostics) = Command::new("wonky-no-such-command").run(None);
+        assert!(matches!(
+            stdout,
+            Err(CommandError::Spawn(io::ErrorKind::NotFound))
+        ));
+        assert_eq!(diagnostics.status, None);
+    }
+
+    fn vars() -> HashMap<String, String> {
+        vec![("SENSOR", "k10temp"), ("DIR", "/tmp")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn expands_variables_from_env_table() {
+        assert_eq!(expand("$SENSOR", &vars()), "k10temp");
+        assert_eq!(expand("${DIR}/x", &vars()), "/tmp/x");
+        assert_eq!(expand("a-$SENSOR-b", &vars()), "a-k10temp-b");
+        assert_eq!(expand("$DIR$SENSOR", &vars()), "/tmpk10temp");
+    }
+
+    #[test]
+    fn leaves_unknown_and_invalid_variables_alone() {
+        assert_eq!(
+            expand("{ print $WONKY_NOT_SET }", &vars()),
+            "{ print $WONKY_NOT_SET }"
+        );
+        assert_eq!(expand("$1 $", &vars()), "$1 $");
+        assert_eq!(expand("${SENSOR", &vars()), "${SENSOR");
+        assert_eq!(expand("${}", &vars()), "${}");
+    }
+
+    #[test]
+    fn expands_leading_tilde_only() {
+        let home = BaseDirs::new()
+            .unwrap()
+            .home_dir()
+            .to_string_lossy()
+            .to_string();
+        assert_eq!(expand("~/scripts", &vars()), format!("{}/scripts", home));
+        assert_eq!(expand("~", &vars()), home);
+        assert_eq!(expand("a~/b", &vars()), "a~/b");
+        assert_eq!(expand("~user", &vars()), "~user");
+    }
+
+    #[test]
+    fn shell_form_is_not_expanded() {
+        let line = CommandLine::Shell {
+            shell: "echo $SENSOR".to_string(),
+        };
+        assert_eq!(line.argv(&vars()).last().unwrap(), "echo $SENSOR");
+
+        let line = CommandLine::from(vec!["echo".to_string(), "$SENSOR".to_string()]);
+        assert_eq!(line.argv(&vars()), vec!["echo", "k10temp"]);
+    }
+
+    /// Stream `script` on a thread of its own, which never returns.
+    fn stream(script: &str) -> mpsc::Receiver<Outcome> {
+        let (tx, rx) = mpsc::channel();
+        let mut cmd = sh(script);
+        thread::spawn(move || {
+            cmd.stream(&|outcome: Outcome| {
+                let _ = tx.send(outcome);
+            })
+        });
+        rx
+    }
+
+    #[test]
+    fn stream_publishes_every_line() {
+        let rx = stream("printf '1\\n2\\n'");
+        let timeout = Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(timeout).unwrap().0.unwrap(), "1");
+        assert_eq!(rx.recv_timeout(timeout).unwrap().0.unwrap(), "2");
+    }
+
+    #[test]
+    fn stream_publishes_failed_exits() {
+        let rx = stream("echo 1; echo oops >&2; exit 3");
+        let timeout = Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(timeout).unwrap().0.unwrap(), "1");
+
+        let (stdout, diagnostics) = rx.recv_timeout(timeout).unwrap();
+        assert!(matches!(stdout, Err(CommandError::Exit(Some(3)))));
+        assert_eq!(diagnostics.status, Some(3));
+        assert_eq!(diagnostics.stderr, vec!["oops"]);
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::Result;
+use serde::Deserialize;
+use tinybit::{widgets::Text, ScreenPos, Viewport};
+
+use crate::layout::{self, Anchor, Rect, Track};
+use crate::scheduler::Scheduler;
+
+use super::inline::Inline;
+use super::separator::SeperatorTheme;
+use super::{Element, Widget};
+
+/// Which way a container lays out its widgets.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Direction {
+    /// Side by side.
+    Row,
+    /// One below the other.
+    #[default]
+    Column,
+}
+
+/// Widgets grouped together, side by side in a `Row` or one below the
+/// other in a `Column` or `Box`, optionally framed by a titled border.
+#[derive(Debug, Deserialize)]
+pub struct Container {
+    title: Option<String>,
+    /// Draw a border around the widgets, on by default for a `Box`.
+    border: Option<bool>,
+    widgets: Vec<Element>,
+    /// Widths of the widgets in a row, see `Track`, an equal share
+    /// for every widget left out.
+    #[serde(default)]
+    sizes: Vec<Track>,
+    /// Cells between the widgets in a row.
+    #[serde(default = "default_gap")]
+    gap: u16,
+
+    #[serde(default)]
+    pub right: bool,
+    #[serde(default)]
+    pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+
+    #[serde(default)]
+    pub theme: SeperatorTheme,
+
+    #[serde(skip_deserializing)]
+    direction: Direction,
+}
+
+impl Container {
+    pub fn init(
+        &mut self,
+        scheduler: &mut Scheduler,
+        direction: Direction,
+        border: bool,
+    ) -> Result<()> {
+        self.direction = direction;
+        self.border = Some(self.border.unwrap_or(border));
+        self.theme.init();
+
+        self.widgets
+            .iter_mut()
+            .try_for_each(|widget| widget.init(scheduler))
+    }
+
+    /// Expand the widgets that stand in for several.
+    pub fn discover(&mut self) {
+        self.widgets = std::mem::take(&mut self.widgets)
+            .into_iter()
+            .flat_map(Element::discover)
+            .collect();
+    }
+
+    fn has_border(&self) -> bool {
+        self.border.unwrap_or(false)
+    }
+
+    /// Lines taken by border and title around the widgets, above and below.
+    fn frame(&self) -> (u16, u16) {
+        match (self.has_border(), &self.title) {
+            (true, _) => (1, 1),
+            (false, Some(_)) => (1, 0),
+            (false, None) => (0, 0),
+        }
+    }
+
+    fn draw_border(&self, viewport: &mut Viewport, area: Rect) {
+        let (fg, bg) = (self.theme.fg_color, self.theme.bg_color);
+        let inner = area.width.saturating_sub(2) as usize;
+        if area.width < 2 || area.height < 2 {
+            return;
+        }
+
+        let title = match &self.title {
+            Some(t) => format!(" {} ", t).chars().take(inner).collect(),
+            None => String::new(),
+        };
+        let top = format!("┌{}{}┐", title, "─".repeat(inner - title.chars().count()));
+        viewport.draw_widget(&Text::new(top, fg, bg), area.pos());
+
+        for y in area.y + 1..area.y + area.height - 1 {
+            viewport.draw_widget(&Text::new("│", fg, bg), ScreenPos::new(area.x, y));
+            viewport.draw_widget(
+                &Text::new("│", fg, bg),
+                ScreenPos::new(area.x + area.width - 1, y),
+            );
+        }
+
+        let bottom = format!("└{}┘", "─".repeat(inner));
+        viewport.draw_widget(
+            &Text::new(bottom, fg, bg),
+            ScreenPos::new(area.x, area.y + area.height - 1),
+        );
+    }
+}
+
+fn default_gap() -> u16 {
+    2
+}
+
+//----------------------------------------------------------------------------+
+// Trait Impl                                                                 |
+//----------------------------------------------------------------------------+
+
+impl Widget for Container {
+    fn update(&mut self) -> Result<()> {
+        self.widgets
+            .iter_mut()
+            .try_for_each(|widget| widget.widget_mut().update())
+    }
+
+    fn update_and_draw(
+        &mut self,
+        viewport: &mut Viewport,
+        area: Rect,
+        resized: &bool,
+    ) -> Result<()> {
+        let (above, below) = self.frame();
+
+        // Keep a space between the border and the widgets
+        let inner = if self.has_border() {
+            self.draw_border(viewport, area);
+            Rect::new(
+                area.x + 2,
+                area.y + above,
+                area.width.saturating_sub(4),
+                area.height.saturating_sub(above + below),
+            )
+        } else {
+            if let Some(t) = &self.title {
+                viewport.draw_widget(
+                    &Text::new(t, self.theme.fg_color, self.theme.bg_color),
+                    area.pos(),
+                );
+            }
+            Rect::new(
+                area.x,
+                area.y + above,
+                area.width,
+                area.height.saturating_sub(above),
+            )
+        };
+
+        let mut widgets = self
+            .widgets
+            .iter_mut()
+            .map(Element::widget_mut)
+            .collect::<Vec<_>>();
+
+        match self.direction {
+            Direction::Column => {
+                layout::stack(&mut widgets, viewport, inner, Anchor::Top, resized)?;
+            }
+            Direction::Row => {
+                let mut sizes = self.sizes.clone();
+                sizes.resize(widgets.len(), Track::Flex(1));
+                let columns = layout::split(&sizes, inner.width, self.gap);
+
+                for (widget, (x, width)) in widgets.iter_mut().zip(columns) {
+                    let height = widget.vertical_size() as u16;
+                    let area = Rect::new(inner.x + x, inner.y, width, height);
+                    widget.update_and_draw(viewport, area, resized)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.bottom
+    }
+
+    fn is_right(&self) -> bool {
+        self.right
+    }
+
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    fn vertical_size(&self) -> u8 {
+        let (above, below) = self.frame();
+        let sizes = self.widgets.iter().map(|w| w.widget().vertical_size());
+
+        let content = match self.direction {
+            Direction::Row => sizes.max().unwrap_or(0),
+            Direction::Column => sizes.fold(0_u8, u8::saturating_add),
+        };
+        content.saturating_add((above + below) as u8)
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        let mut line = Inline::new(pos, width).spaced(self.gap);
+
+        if let Some(t) = &self.title {
+            line.text(viewport, t, (self.theme.fg_color, self.theme.bg_color));
+        }
+        for widget in self.widgets.iter_mut().map(Element::widget_mut) {
+            line.widget(|pos, width| widget.draw_inline(viewport, pos, width))?;
+        }
+
+        Ok(line.used())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        self.widgets.iter_mut().map(Element::widget_mut).collect()
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::CommandError;
+
+/// Either one selector for the value, or one each for value, max and label:
+///
+/// ```toml
+/// json_path = "memory.used"
+/// json_path = { value = "memory.used", max = "memory.total", label = "host" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Selector<T> {
+    Value(T),
+    Parts {
+        value: Option<T>,
+        max: Option<T>,
+        label: Option<T>,
+    },
+}
+
+impl<T> Selector<T> {
+    fn parts(&self) -> [Option<&T>; 3] {
+        match self {
+            Selector::Value(value) => [Some(value), None, None],
+            Selector::Parts { value, max, label } => [value.as_ref(), max.as_ref(), label.as_ref()],
+        }
+    }
+}
+
+/// Pulls value, max and label out of command output that is more than
+/// a bare number. At most one of `json_path`, `regex` and `field` is used.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Extract {
+    json_path: Option<Selector<String>>,
+    /// Named groups `value`, `max` and `label`, otherwise the first group.
+    regex: Option<String>,
+    /// 1-based, like `cut` and `awk`.
+    field: Option<Selector<usize>>,
+    /// Whitespace if left out.
+    delimiter: Option<String>,
+
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+/// What an extractor found, all still unparsed.
+#[derive(Debug, Default)]
+pub struct Extracted {
+    pub value: Option<String>,
+    pub max: Option<String>,
+    pub label: Option<String>,
+}
+
+impl Extract {
+    pub fn is_set(&self) -> bool {
+        self.json_path.is_some() || self.regex.is_some() || self.field.is_some()
+    }
+
+    /// Whether a label is picked out as well, known before any output
+    /// comes in so widgets can set aside a row for it.
+    pub fn has_label(&self) -> bool {
+        self.json_path
+            .as_ref()
+            .is_some_and(|s| s.parts()[2].is_some())
+            || self.field.as_ref().is_some_and(|s| s.parts()[2].is_some())
+            || self
+                .compiled
+                .as_ref()
+                .is_some_and(|r| r.capture_names().flatten().any(|name| name == "label"))
+    }
+
+    /// Compile the regex, so a typo shows up when the config is loaded.
+    pub fn init(&mut self) -> Result<()> {
+        if let Some(regex) = &self.regex {
+            self.compiled = Some(Regex::new(regex).context(format!("invalid regex {:?}", regex))?);
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, output: &str) -> Result<Extracted, CommandError> {
+        if let Some(selector) = &self.json_path {
+            let json = serde_json::from_str::<Value>(output)
+                .map_err(|e| CommandError::Extract(format!("invalid json: {}", e)))?;
+
+            let [value, max, label] = selector.parts().map(|path| {
+                path.map(|path| {
+                    json_path(&json, path)
+                        .ok_or_else(|| CommandError::Extract(format!("no json_path {}", path)))
+                })
+                .transpose()
+            });
+
+            return Ok(Extracted {
+                value: value?,
+                max: max?,
+                label: label?,
+            });
+        }
+
+        if let Some(regex) = &self.compiled {
+            let captures = regex
+                .captures(output)
+                .ok_or_else(|| CommandError::Extract(format!("no match for {}", regex)))?;
+            let group = |name| captures.name(name).map(|m| m.as_str().to_string());
+
+            return Ok(Extracted {
+                value: group("value").or_else(|| {
+                    let first = captures.get(1).or_else(|| captures.get(0));
+                    first.map(|m| m.as_str().to_string())
+                }),
+                max: group("max"),
+                label: group("label"),
+            });
+        }
+
+        if let Some(selector) = &self.field {
+            let fields = match &self.delimiter {
+                Some(delimiter) => output.split(delimiter.as_str()).collect::<Vec<_>>(),
+                None => output.split_whitespace().collect(),
+            };
+
+            let [value, max, label] = selector.parts().map(|field| {
+                field
+                    .map(|&n| {
+                        n.checked_sub(1)
+                            .and_then(|i| fields.get(i))
+                            .map(|f| f.trim().to_string())
+                            .ok_or_else(|| CommandError::Extract(format!("no field {}", n)))
+                    })
+                    .transpose()
+            });
+
+            return Ok(Extracted {
+                value: value?,
+                max: max?,
+                label: label?,
+            });
+        }
+
+        Ok(Extracted {
+            value: Some(output.to_string()),
+            ..Extracted::default()
+        })
+    }
+}
+
+/// Split the output of a meter's `command` into value and max, given
+/// as `value/max`, `value max` or `{"value": .., "max": ..}`.
+pub fn value_and_max(output: &str) -> Result<Extracted, CommandError> {
+    let output = output.trim();
+
+    if output.starts_with('{') {
+        let json = serde_json::from_str::<Value>(output)
+            .map_err(|e| CommandError::Extract(format!("invalid json: {}", e)))?;
+
+        return Ok(Extracted {
+            value: json_path(&json, "value"),
+            max: json_path(&json, "max"),
+            label: None,
+        });
+    }
+
+    let (value, max) = output
+        .split_once('/')
+        .or_else(|| output.split_once(char::is_whitespace))
+        .ok_or_else(|| CommandError::Parse(output.to_string()))?;
+
+    Ok(Extracted {
+        value: Some(value.trim().to_string()),
+        max: Some(max.trim().to_string()),
+        label: None,
+    })
+}
+
+/// Follow a dotted path like `$.disks[0].size` or `disks.0.size`.
+fn json_path(json: &Value, path: &str) -> Option<String> {
+    let path = path
+        .trim_start_matches('$')
+        .replace('[', ".")
+        .replace(']', "");
+
+    let found =
+        path.split('.')
+            .filter(|key| !key.is_empty())
+            .try_fold(json, |json, key| match json {
+                Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+                _ => json.get(key),
+            })?;
+
+    Some(match found {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Extract {
+        let mut extract: Extract = toml::from_str(toml).unwrap();
+        extract.init().unwrap();
+        extract
+    }
+
+    fn parts(extracted: Extracted) -> [Option<String>; 3] {
+        [extracted.value, extracted.max, extracted.label]
+    }
+
+    fn some(parts: [&str; 3]) -> [Option<String>; 3] {
+        parts.map(|p| Some(p.to_string()).filter(|p| !p.is_empty()))
+    }
+
+    #[test]
+    fn unset_passes_output_through() {
+        let extract = config("");
+        assert!(!extract.is_set());
+        assert_eq!(parts(extract.apply("42").unwrap()), some(["42", "", ""]));
+    }
+
+    #[test]
+    fn json_path_follows_keys_and_indices() {
+        let output = r#"{"host": "box", "disks": [{"size": 10}, {"size": 20.5}]}"#;
+
+        let extract = config(r#"json_path = "$.disks[1].size""#);
+        assert_eq!(
+            parts(extract.apply(output).unwrap()),
+            some(["20.5", "", ""])
+        );
+
+        let extract = config(
+            r#"json_path = { value = "disks.0.size", max = "disks.1.size", label = "host" }"#,
+        );
+        assert_eq!(
+            parts(extract.apply(output).unwrap()),
+            some(["10", "20.5", "box"])
+        );
+    }
+
+    #[test]
+    fn json_path_errors() {
+        let extract = config(r#"json_path = "missing""#);
+        assert!(matches!(extract.apply("{}"), Err(CommandError::Extract(_))));
+        assert!(matches!(
+            extract.apply("not json"),
+            Err(CommandError::Extract(_))
+        ));
+    }
+
+    #[test]
+    fn regex_named_groups_or_first_group() {
+        let extract = config(r#"regex = '(?P<label>\w+): (?P<value>\d+)/(?P<max>\d+)'"#);
+        assert_eq!(
+            parts(extract.apply("sda: 12/40").unwrap()),
+            some(["12", "40", "sda"])
+        );
+
+        let extract = config(r#"regex = 'temp (\d+)'"#);
+        assert_eq!(
+            parts(extract.apply("temp 51 C").unwrap()),
+            some(["51", "", ""])
+        );
+
+        let extract = config(r#"regex = '\d+'"#);
+        assert_eq!(
+            parts(extract.apply("up 3 days").unwrap()),
+            some(["3", "", ""])
+        );
+        assert!(matches!(
+            extract.apply("none"),
+            Err(CommandError::Extract(_))
+        ));
+    }
+
+    #[test]
+    fn label_selectors() {
+        assert!(!config("").has_label());
+        assert!(!config("json_path = \"a.b\"").has_label());
+        assert!(config(r#"json_path = { value = "a", label = "b" }"#).has_label());
+        assert!(!config(r#"regex = '(\d+)'"#).has_label());
+        assert!(config(r#"regex = '(?P<label>\w+): (?P<value>\d+)'"#).has_label());
+        assert!(!config("field = { value = 2, max = 3 }").has_label());
+        assert!(config("field = { value = 2, label = 1 }").has_label());
+    }
+
+    #[test]
+    fn invalid_regex_fails_init() {
+        let mut extract: Extract = toml::from_str("regex = '('").unwrap();
+        assert!(extract.init().is_err());
+    }
+
+    #[test]
+    fn field_is_one_based() {
+        let extract = config("field = 2");
+        assert_eq!(parts(extract.apply("a  7 b").unwrap()), some(["7", "", ""]));
+
+        let extract = config("field = { value = 3, max = 2, label = 1 }\ndelimiter = ','");
+        assert_eq!(
+            parts(extract.apply("eth0, 100, 12").unwrap()),
+            some(["12", "100", "eth0"])
+        );
+
+        let extract = config("field = 0");
+        assert!(matches!(
+            extract.apply("a b"),
+            Err(CommandError::Extract(_))
+        ));
+        let extract = config("field = 3");
+        assert!(matches!(
+            extract.apply("a b"),
+            Err(CommandError::Extract(_))
+        ));
+    }
+
+    #[test]
+    fn value_and_max_forms() {
+        assert_eq!(parts(value_and_max("3/8").unwrap()), some(["3", "8", ""]));
+        assert_eq!(parts(value_and_max(" 3 8 ").unwrap()), some(["3", "8", ""]));
+        assert_eq!(
+            parts(value_and_max(r#"{"value": 3.5, "max": 8}"#).unwrap()),
+            some(["3.5", "8", ""])
+        );
+        assert!(matches!(value_and_max("3"), Err(CommandError::Parse(_))));
+    }
+}
@@ -0,0 +1,133 @@
+use serde::Deserialize;
+
+const BYTES: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+const BITS: [&str; 6] = ["b", "kb", "Mb", "Gb", "Tb", "Pb"];
+const SI: [&str; 6] = ["", "k", "M", "G", "T", "P"];
+
+/// How a number is written out in a reading.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Bytes, scaled by 1024: `15.2GiB`.
+    Bytes,
+    /// Bits, scaled by 1000: `94.1Mb`.
+    Bits,
+    /// Plain SI prefixes, scaled by 1000: `3.4k`.
+    Si,
+    /// Seconds, as the two largest units: `3h12m`.
+    Duration,
+    /// A value that already is a percentage: `57%`.
+    Percent,
+}
+
+impl Format {
+    /// Write `value` out, with `decimals` overriding the default precision.
+    pub fn apply(self, value: f64, decimals: Option<usize>) -> String {
+        match self {
+            Format::Bytes => scaled(value, 1024.0, &BYTES, decimals),
+            Format::Bits => scaled(value, 1000.0, &BITS, decimals),
+            Format::Si => scaled(value, 1000.0, &SI, decimals),
+            Format::Duration => duration(value),
+            Format::Percent => format!("{:.*}%", decimals.unwrap_or(0), value),
+        }
+    }
+}
+
+/// A plain number with `decimals` decimals, or whole numbers and at most
+/// two decimals if unset.
+pub fn number(value: f64, decimals: Option<usize>) -> String {
+    match decimals {
+        Some(decimals) => format!("{:.*}", decimals, value),
+        None => {
+            let number = format!("{:.2}", value);
+            let number = number.trim_end_matches('0').trim_end_matches('.');
+            // -0.001 should not read as "-0"
+            if number == "-0" { "0" } else { number }.to_string()
+        }
+    }
+}
+
+fn scaled(value: f64, step: f64, units: &[&str], decimals: Option<usize>) -> String {
+    let mut value = value;
+    let mut unit = 0;
+
+    while value.abs() >= step && unit < units.len() - 1 {
+        value /= step;
+        unit += 1;
+    }
+
+    // Unscaled values are whole bytes and bits, no point in decimals
+    let decimals = decimals.unwrap_or(if unit == 0 { 0 } else { 1 });
+    format!("{:.*}{}", decimals, value, units[unit])
+}
+
+fn duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+
+    let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+    let largest = units
+        .iter()
+        .position(|&(_, size)| seconds >= size)
+        .unwrap_or(units.len() - 1);
+
+    units[largest..]
+        .iter()
+        .take(2)
+        .enumerate()
+        .map(|(i, &(name, size))| {
+            let amount = match i {
+                0 => seconds / size,
+                _ => seconds % units[largest].1 / size,
+            };
+            format!("{}{}", amount, name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_scale_by_1024() {
+        assert_eq!(Format::Bytes.apply(512.0, None), "512B");
+        assert_eq!(Format::Bytes.apply(1536.0, None), "1.5KiB");
+        assert_eq!(Format::Bytes.apply(16_320_875_724.8, None), "15.2GiB");
+        assert_eq!(Format::Bytes.apply(1536.0, Some(2)), "1.50KiB");
+        assert_eq!(Format::Bytes.apply(-2048.0, None), "-2.0KiB");
+    }
+
+    #[test]
+    fn bits_and_si_scale_by_1000() {
+        assert_eq!(Format::Bits.apply(94_100_000.0, None), "94.1Mb");
+        assert_eq!(Format::Si.apply(3400.0, None), "3.4k");
+        assert_eq!(Format::Si.apply(999.0, None), "999");
+        // Past the largest unit the number just grows
+        assert_eq!(Format::Si.apply(2e18, None), "2000.0P");
+    }
+
+    #[test]
+    fn duration_shows_two_largest_units() {
+        assert_eq!(Format::Duration.apply(0.0, None), "0s");
+        assert_eq!(Format::Duration.apply(45.0, None), "45s");
+        assert_eq!(Format::Duration.apply(11_520.0, None), "3h12m");
+        assert_eq!(Format::Duration.apply(90_061.0, None), "1d1h");
+        assert_eq!(Format::Duration.apply(3600.0, None), "1h0m");
+        assert_eq!(Format::Duration.apply(-5.0, None), "0s");
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(Format::Percent.apply(57.4, None), "57%");
+        assert_eq!(Format::Percent.apply(57.45, Some(1)), "57.5%");
+    }
+
+    #[test]
+    fn plain_numbers_trim_trailing_zeros() {
+        assert_eq!(number(3.0, None), "3");
+        assert_eq!(number(3.10, None), "3.1");
+        assert_eq!(number(1.23456, None), "1.23");
+        assert_eq!(number(-0.001, None), "0");
+        assert_eq!(number(3.0, Some(2)), "3.00");
+    }
+}
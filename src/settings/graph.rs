@@ -0,0 +1,404 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Deserialize;
+use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
+
+use crate::layout::Rect;
+use crate::popup::Popup;
+use crate::scheduler::Scheduler;
+
+use super::colors::Colors;
+use super::inline::Inline;
+use super::{parse_ansi, Probe, Widget, PALETTE};
+
+/// How many sub-cell dots make up a cell, across and down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Render {
+    /// 2x4 dots per cell.
+    #[default]
+    Braille,
+    /// 1x2 dots per cell, for fonts without braille.
+    Halfblock,
+}
+
+impl Render {
+    fn resolution(self) -> (usize, usize) {
+        match self {
+            Render::Braille => (2, 4),
+            Render::Halfblock => (1, 2),
+        }
+    }
+
+    /// Glyph for a cell with the dots in `dots` set, indexed `[x][y]`.
+    fn glyph(self, dots: &[[bool; 4]; 2]) -> char {
+        match self {
+            Render::Braille => {
+                // Braille dot numbering, left column then right
+                let bits = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+                let code = (0..2)
+                    .flat_map(|x| (0..4).map(move |y| (x, y)))
+                    .filter(|&(x, y)| dots[x][y])
+                    .fold(0x2800, |code, (x, y)| code | bits[x][y]);
+                char::from_u32(code).unwrap_or(' ')
+            }
+            Render::Halfblock => match (dots[0][0], dots[0][1]) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            },
+        }
+    }
+}
+
+/// The dots set in a cell, indexed `[x][y]`, and the color of the
+/// series that was drawn there last.
+type Cell = ([[bool; 4]; 2], Option<Color>);
+
+/// One line on the graph.
+#[derive(Debug, Deserialize)]
+pub struct Series {
+    title: Option<String>,
+    /// ANSI color, picked from a palette if left out.
+    color: Option<u8>,
+
+    #[serde(flatten)]
+    probe: Probe,
+
+    #[serde(skip_deserializing)]
+    fg_color: Option<Color>,
+    #[serde(skip_deserializing)]
+    samples: VecDeque<(Instant, f64)>,
+}
+
+/// A time-series chart of one or more series, several rows high.
+#[derive(Debug, Deserialize)]
+pub struct Graph {
+    title: Option<String>,
+    unit: Option<String>,
+    series: Vec<Series>,
+
+    /// Rows used by the chart itself, the title line comes on top.
+    #[serde(default = "default_height")]
+    height: u8,
+    /// Seconds of history across the width of the graph.
+    #[serde(default = "default_window")]
+    window: u64,
+    #[serde(default)]
+    render: Render,
+    /// Fill the area below each line.
+    #[serde(default)]
+    fill: bool,
+
+    #[serde(default)]
+    pub right: bool,
+    #[serde(default)]
+    pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+
+    #[serde(default)]
+    pub theme: Colors,
+
+    #[serde(skip_deserializing)]
+    selected: bool,
+}
+
+impl Graph {
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        for (n, series) in self.series.iter_mut().enumerate() {
+            series.probe.init(scheduler)?;
+            series.fg_color = parse_ansi(series.color.or(Some(PALETTE[n % PALETTE.len()])));
+        }
+        Ok(())
+    }
+
+    /// Range of the y axis: the widest known range of the series, or
+    /// whatever the samples span, including zero.
+    fn bounds(&self) -> (f64, f64) {
+        let known = self
+            .series
+            .iter()
+            .map(|s| &s.probe)
+            .filter(|p| p.max_value > p.min_value);
+
+        let (low, high) = known.fold((f64::MAX, f64::MIN), |(low, high), p| {
+            (low.min(p.min_value), high.max(p.max_value))
+        });
+        if low < high {
+            return (low, high);
+        }
+
+        let values = self
+            .series
+            .iter()
+            .flat_map(|s| s.samples.iter().map(|&(_, v)| v));
+        let (low, high) = values.fold((0.0_f64, f64::MIN), |(low, high), v| {
+            (low.min(v), high.max(v))
+        });
+
+        if high > low {
+            (low, high)
+        } else {
+            (low, low + 1.0)
+        }
+    }
+
+    /// Plot every series on a grid of dots `columns` by `rows` cells large.
+    fn plot(&self, columns: usize, rows: usize) -> Vec<Vec<Cell>> {
+        let mut cells = vec![vec![([[false; 4]; 2], None); columns]; rows];
+        let (xres, yres) = self.render.resolution();
+        let (width, height) = (columns * xres, rows * yres);
+        if width == 0 || height == 0 {
+            return cells;
+        }
+
+        let (low, high) = self.bounds();
+        let now = Instant::now();
+        let window = self.window.max(1) as f64;
+
+        let mut set = |x: i64, y: i64, color: Option<Color>| {
+            if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                return;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let cell = &mut cells[y / yres][x / xres];
+            cell.0[x % xres][y % yres] = true;
+            cell.1 = color;
+        };
+
+        for series in &self.series {
+            let points = series.samples.iter().map(|&(at, value)| {
+                let age = now.duration_since(at).as_secs_f64() / window;
+                let ratio = ((value - low) / (high - low)).clamp(0.0, 1.0);
+                let x = ((1.0 - age) * (width - 1) as f64).round() as i64;
+                let y = ((1.0 - ratio) * (height - 1) as f64).round() as i64;
+                (x, y)
+            });
+
+            let mut last: Option<(i64, i64)> = None;
+            for (x, y) in points {
+                let (x0, y0) = last.unwrap_or((x, y));
+                last = Some((x, y));
+
+                // Walk the segment from the previous point one dot at a time
+                let steps = (x - x0).abs().max((y - y0).abs()).max(1);
+                for step in 0..=steps {
+                    let px = x0 + (x - x0) * step / steps;
+                    let py = y0 + (y - y0) * step / steps;
+
+                    if self.fill {
+                        (py..height as i64).for_each(|fy| set(px, fy, series.fg_color));
+                    } else {
+                        set(px, py, series.fg_color);
+                    }
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+fn default_height() -> u8 {
+    4
+}
+
+fn default_window() -> u64 {
+    60
+}
+
+//----------------------------------------------------------------------------+
+// Trait Impl                                                                 |
+//----------------------------------------------------------------------------+
+
+impl Widget for Graph {
+    fn update(&mut self) -> Result<()> {
+        let now = Instant::now();
+        // Keep one sample past the window so lines run off the left edge
+        let window = Duration::from_secs(self.window.max(1));
+
+        for series in self.series.iter_mut() {
+            // Stamped with when the job published it, not when drawn
+            if let Some(at) = series.probe.update() {
+                series.samples.push_back((at, series.probe.current_value));
+            }
+
+            while series.samples.len() > 1
+                && series
+                    .samples
+                    .get(1)
+                    .is_some_and(|&(at, _)| now.duration_since(at) > window)
+            {
+                series.samples.pop_front();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_and_draw(
+        &mut self,
+        viewport: &mut Viewport,
+        area: Rect,
+        _resized: &bool,
+    ) -> Result<()> {
+        self.update()?;
+
+        let pos = area.pos();
+        let width = area.width;
+        let rows = self.height.max(1) as u16;
+
+        let stale = self.series.iter().any(|s| s.probe.stale);
+        let fg_color = self.theme.fg(stale);
+        let (title_fg, title_bg) = self.theme.text_colors(stale, self.selected);
+
+        // Title followed by a legend of the series in their colors
+        let mut x = pos.x;
+        if let Some(t) = &self.title {
+            viewport.draw_widget(&Text::new(t, title_fg, title_bg), ScreenPos::new(x, pos.y));
+            x += t.chars().count() as u16 + 1;
+        }
+        for series in &self.series {
+            let name = series
+                .probe
+                .label
+                .as_deref()
+                .or(series.title.as_deref())
+                .unwrap_or("■");
+            let (name, color) = match series.probe.error() {
+                Some(e) => (format!("{}: {}", name, e), self.theme.error_color),
+                None => (name.to_string(), series.fg_color),
+            };
+            viewport.draw_widget(&Text::new(&name, color, None), ScreenPos::new(x, pos.y));
+            x += name.chars().count() as u16 + 1;
+        }
+
+        // Y axis labels, top and bottom, in the format of the first series
+        let (low, high) = self.bounds();
+        let number = |value| match self.series.first() {
+            Some(series) => series.probe.number(value),
+            None => value.to_string(),
+        };
+        let unit = self.unit.as_deref().unwrap_or("");
+        let labels = [
+            format!("{}{}", number(high), unit),
+            format!("{}{}", number(low), unit),
+        ];
+        let axis = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0) as u16 + 1;
+
+        viewport.draw_widget(
+            &Text::new(
+                format!("{:>w$}", labels[0], w = axis as usize - 1),
+                fg_color,
+                None,
+            ),
+            ScreenPos::new(pos.x, pos.y + 1),
+        );
+        viewport.draw_widget(
+            &Text::new(
+                format!("{:>w$}", labels[1], w = axis as usize - 1),
+                fg_color,
+                None,
+            ),
+            ScreenPos::new(pos.x, pos.y + rows),
+        );
+
+        let columns = width.saturating_sub(axis) as usize;
+        for (row, cells) in self.plot(columns, rows as usize).iter().enumerate() {
+            for (column, (dots, color)) in cells.iter().enumerate() {
+                viewport.draw_widget(
+                    &Text::new(
+                        self.render.glyph(dots).to_string(),
+                        if stale { fg_color } else { *color },
+                        self.theme.bg_color,
+                    ),
+                    ScreenPos::new(pos.x + axis + column as u16, pos.y + 1 + row as u16),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.bottom
+    }
+
+    fn is_right(&self) -> bool {
+        self.right
+    }
+
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    fn vertical_size(&self) -> u8 {
+        self.height.max(1).saturating_add(1)
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        self.update()?;
+
+        let stale = self.series.iter().any(|s| s.probe.stale);
+        let fg_color = self.theme.fg(stale);
+        let mut line = Inline::new(pos, width);
+
+        if let Some(t) = &self.title {
+            line.text(viewport, t, self.theme.text_colors(stale, self.selected));
+        }
+
+        // The latest value of every series in its color
+        let unit = self.unit.as_deref().unwrap_or("");
+        for series in &self.series {
+            let name = series.probe.label.as_ref().or(series.title.as_ref());
+            let (text, color) = match (series.probe.error(), series.samples.back()) {
+                (Some(e), _) => (format!("ERR: {}", e), self.theme.error_color),
+                (None, Some(&(_, value))) => (
+                    format!("{}{}", series.probe.number(value), unit),
+                    if stale { fg_color } else { series.fg_color },
+                ),
+                (None, None) => continue,
+            };
+            let text = match name {
+                Some(name) => format!("{} {}", name, text),
+                None => text,
+            };
+            line.text(viewport, &text, (color, None));
+        }
+
+        Ok(line.used())
+    }
+
+    fn details(&self) -> Option<Popup> {
+        let lines = self
+            .series
+            .iter()
+            .enumerate()
+            .flat_map(|(n, series)| {
+                let title = series
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("series {}", n + 1));
+                let details = series
+                    .probe
+                    .details()
+                    .into_iter()
+                    .map(|l| format!("  {}", l));
+                std::iter::once(format!("{}:", title)).chain(details)
+            })
+            .collect();
+
+        Some(Popup::new(
+            self.title.as_deref().unwrap_or("Graph").trim(),
+            lines,
+        ))
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+}
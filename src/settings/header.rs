@@ -0,0 +1,59 @@
+use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
+
+use crate::layout::Rect;
+
+use super::{draw_error, CommandError, Inline};
+
+/// Title and reading of a widget, or the error it failed with in place
+/// of the reading. Drawn on the line above a bar, or around it in a
+/// status bar.
+pub struct Header<'a> {
+    pub title: Option<&'a str>,
+    pub reading: Option<String>,
+    pub error: Option<&'a CommandError>,
+    pub colors: (Option<Color>, Option<Color>),
+    pub error_color: Option<Color>,
+}
+
+impl Header<'_> {
+    /// Title on the left of the first line of `area`, the reading or
+    /// error right aligned.
+    pub fn draw(&self, viewport: &mut Viewport, area: Rect) {
+        let pos = area.pos();
+        let (fg, bg) = self.colors;
+
+        if let (Some(reading), None) = (&self.reading, self.error) {
+            let reading = Text::new(reading, fg, bg);
+            let x = area.width.saturating_sub(reading.0.chars().count() as u16);
+            viewport.draw_widget(&reading, ScreenPos::new(pos.x + x, pos.y));
+        }
+
+        if let Some(title) = self.title {
+            viewport.draw_widget(&Text::new(title, fg, bg), pos);
+        }
+
+        if let Some(error) = self.error {
+            draw_error(viewport, error, self.error_color, pos, area.width);
+        }
+    }
+
+    /// The title as the first part of `line`.
+    pub fn draw_title(&self, viewport: &mut Viewport, line: &mut Inline) {
+        if let Some(title) = self.title {
+            line.text(viewport, title, self.colors);
+        }
+    }
+
+    /// The reading or error as the next part of `line`.
+    pub fn draw_reading(&self, viewport: &mut Viewport, line: &mut Inline) {
+        match (self.error, &self.reading) {
+            (Some(error), _) => line.text(
+                viewport,
+                &format!("ERR: {}", error),
+                (self.error_color, None),
+            ),
+            (None, Some(reading)) => line.text(viewport, reading, self.colors),
+            (None, None) => {}
+        }
+    }
+}
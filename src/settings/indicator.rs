@@ -1,58 +1,179 @@
-use std::time::Instant;
+use std::collections::HashMap;
+use std::time::Duration;
 
 use anyhow::Result;
 use serde::Deserialize;
 use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
 
-use super::{CommandExt, Widget};
+use crate::layout::Rect;
+use crate::popup::Popup;
+use crate::scheduler::{Latest, Scheduler};
+
+use super::inline::Inline;
+use super::sizing::Sizing;
+use super::{
+    draw_error, format, parse_ansi, CommandError, CommandExt, CommandLine, Diagnostics, Extract,
+    Format, Outcome, Sample, Source, SourceOptions, Widget,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct IndicatorTheme {
+    #[serde(default = "super::stale_fg")]
+    stale_fg: Option<u8>,
+    stale_bg: Option<u8>,
+    #[serde(default = "super::error_fg")]
+    error_fg: Option<u8>,
+
+    #[serde(default)]
+    pub stale_fg_color: Option<Color>,
+    #[serde(default)]
+    pub stale_bg_color: Option<Color>,
+    #[serde(default)]
+    pub error_color: Option<Color>,
+}
+
+impl IndicatorTheme {
+    pub fn init(&mut self) {
+        self.stale_fg_color = parse_ansi(self.stale_fg);
+        self.stale_bg_color = parse_ansi(self.stale_bg);
+        self.error_color = parse_ansi(self.error_fg);
+    }
+}
+
+impl Default for IndicatorTheme {
+    fn default() -> Self {
+        Self {
+            stale_fg: super::stale_fg(),
+            stale_bg: None,
+            error_fg: super::error_fg(),
+            stale_fg_color: None,
+            stale_bg_color: None,
+            error_color: None,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct Indicator {
     title: Option<String>,
-    command: Vec<String>,
+    command: Option<CommandLine>,
+    source: Option<Source>,
+    #[serde(flatten)]
+    source_options: SourceOptions,
+    #[serde(flatten)]
+    extract: Extract,
+    /// Scale numbers to bytes, durations and the like, see `Format`.
+    format: Option<Format>,
+    decimals: Option<usize>,
+    #[serde(default)]
     frequency: u64,
+    timeout: Option<u64>,
+    #[serde(default)]
+    stream: bool,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    cwd: Option<String>,
 
+    #[serde(default)]
     pub right: bool,
+    #[serde(default)]
     pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+    #[serde(flatten)]
+    pub sizing: Sizing,
 
     fg_color: Option<Color>,
     bg_color: Option<Color>,
 
+    #[serde(default)]
+    pub theme: IndicatorTheme,
+
+    #[serde(skip_deserializing)]
+    reading: Option<String>,
+    #[serde(skip_deserializing)]
+    stale: bool,
+    #[serde(skip_deserializing)]
+    error: Option<CommandError>,
     #[serde(skip_deserializing)]
-    value: bool,
+    diagnostics: Diagnostics,
     #[serde(skip_deserializing)]
-    reading: String,
+    selected: bool,
     #[serde(skip_deserializing)]
-    timer: Option<Instant>,
+    output: Option<Latest<Outcome>>,
+    #[serde(skip_deserializing)]
+    source_output: Option<Latest<Result<Sample, CommandError>>>,
 }
 
 impl Indicator {
-    pub fn update(&mut self) -> Result<()> {
-        if self
-            .timer
-            .map(|t| t.elapsed().as_secs() > self.frequency)
-            .unwrap_or(true)
-        {
-            self.timer = Some(Instant::now());
+    /// Keep the last good reading around, but mark it stale until
+    /// a fresh one arrives.
+    fn receive<T>(&mut self, reading: Result<T, CommandError>) -> Option<T> {
+        match reading {
+            Ok(reading) => {
+                self.stale = false;
+                self.error = None;
+                Some(reading)
+            }
+            Err(CommandError::TimedOut) => {
+                self.stale = true;
+                None
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
 
-            if let Some(mut cmd) = super::construct_command(&self.command) {
-                let output = cmd.get_stdout();
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        let timeout = self.timeout.map(Duration::from_secs);
+        self.extract.init()?;
 
-                self.parse_output(output);
-            }
+        if let Some(source) = self.source {
+            let sampler = source.sampler(&self.source_options);
+            self.diagnostics.command = source.to_string();
+            self.source_output = Some(scheduler.every(self.frequency, sampler));
+        } else if let Some(mut cmd) = self
+            .command
+            .as_ref()
+            .and_then(|c| super::construct_command(c, &self.env, self.cwd.as_deref()))
+        {
+            self.output = Some(if self.stream {
+                scheduler.stream(move |publish| cmd.stream(publish))
+            } else {
+                scheduler.every(self.frequency, move || cmd.run(timeout))
+            });
         }
 
         Ok(())
     }
 
-    pub fn init(&mut self) -> Result<()> {
-        if let Some(output) =
-            super::construct_command(&self.command).map(|mut cmd| cmd.get_stdout())
-        {
-            self.parse_output(output);
+    /// Format `value` if it is a number and a format or decimals are set.
+    fn number(&self, value: String) -> String {
+        match (value.parse::<f64>(), self.format) {
+            (Ok(n), Some(format)) => format.apply(n, self.decimals),
+            (Ok(n), None) if self.decimals.is_some() => format::number(n, self.decimals),
+            _ => value,
         }
+    }
 
-        Ok(())
+    /// Colors of the reading, swapped while selected.
+    fn colors(&self) -> (Option<Color>, Option<Color>) {
+        match (self.stale, self.selected) {
+            (true, false) => (self.theme.stale_fg_color, self.theme.stale_bg_color),
+            (true, true) => (self.theme.stale_bg_color, self.theme.stale_fg_color),
+            (false, false) => (self.fg_color, self.bg_color),
+            (false, true) => (self.bg_color, self.fg_color),
+        }
+    }
+
+    /// The reading, or the title until the first one comes in.
+    fn text(&self) -> &str {
+        match (&self.reading, &self.title) {
+            (Some(r), _) | (None, Some(r)) => r.as_str(),
+            _ => "",
+        }
     }
 
     fn parse_output(&mut self, output: String) {
@@ -60,7 +181,7 @@ impl Indicator {
 
         self.fg_color = Color::parse_ansi(&format!("5;{}", split.next().unwrap_or("0"))[..]);
         self.bg_color = Color::parse_ansi(&format!("5;{}", split.next().unwrap_or("2"))[..]);
-        self.reading = split.collect();
+        self.reading = Some(split.collect());
     }
 }
 
@@ -69,26 +190,74 @@ impl Indicator {
 //----------------------------------------------------------------------------+
 
 impl Widget for Indicator {
+    fn update(&mut self) -> Result<()> {
+        if let Some((output, diagnostics)) = self.output.as_ref().and_then(Latest::take) {
+            self.diagnostics = diagnostics;
+            if self.extract.is_set() {
+                let text = output.and_then(|o| self.extract.apply(&o));
+                if let Some(extracted) = self.receive(text) {
+                    let value = extracted.value.map(|v| self.number(v));
+                    let parts = vec![extracted.label, value];
+                    self.reading = Some(parts.into_iter().flatten().collect::<Vec<_>>().join(" "));
+                }
+            } else if let Some(output) = self.receive(output) {
+                self.parse_output(output);
+            }
+        }
+
+        if let Some(sample) = self.source_output.as_ref().and_then(Latest::take) {
+            if let Some(sample) = self.receive(sample) {
+                self.reading = Some(match (self.format, self.decimals) {
+                    (None, None) => sample.text,
+                    _ => self.number(sample.value.to_string()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     fn update_and_draw(
         &mut self,
         viewport: &mut Viewport,
-        pos: &mut ScreenPos,
+        area: Rect,
         _resized: &bool,
     ) -> Result<()> {
         self.update()?;
 
+        let (outer, inner) = self.sizing.apply(area);
+        let (fg_color, bg_color) = self.colors();
+
         viewport.draw_widget(
-            &Text::new(
-                " ".repeat((viewport.size.width / 2 - 2) as usize),
-                None,
-                self.bg_color,
-            ),
-            *pos,
+            &Text::new(" ".repeat(outer.width as usize), None, bg_color),
+            outer.pos(),
         );
 
+        if let Some(e) = &self.error {
+            draw_error(
+                viewport,
+                e,
+                self.theme.error_color,
+                outer.pos(),
+                outer.width,
+            );
+            return Ok(());
+        }
+
+        let text = self
+            .text()
+            .chars()
+            .take(inner.width as usize)
+            .collect::<String>();
+        let x = inner.x
+            + self
+                .sizing
+                .align
+                .offset(text.chars().count() as u16, inner.width);
+
         viewport.draw_widget(
-            &Text::new(&self.reading, self.fg_color, self.bg_color),
-            ScreenPos::new(pos.x, pos.y),
+            &Text::new(text, fg_color, bg_color),
+            ScreenPos::new(x, inner.y),
         );
 
         Ok(())
@@ -102,7 +271,39 @@ impl Widget for Indicator {
         self.right
     }
 
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
     fn vertical_size(&self) -> u8 {
-        1
+        self.sizing.height(1)
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        self.update()?;
+
+        // A pill, padded out in its background color
+        let mut line = Inline::new(pos, width);
+        match &self.error {
+            Some(e) => line.text(
+                viewport,
+                &format!("ERR: {}", e),
+                (self.theme.error_color, None),
+            ),
+            None => line.text(viewport, &format!(" {} ", self.text()), self.colors()),
+        }
+
+        Ok(line.used())
+    }
+
+    fn details(&self) -> Option<Popup> {
+        Some(Popup::new(
+            self.title.as_deref().unwrap_or("Indicator").trim(),
+            self.diagnostics.lines(self.error.as_ref()),
+        ))
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
     }
 }
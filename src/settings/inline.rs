@@ -0,0 +1,90 @@
+use anyhow::Result;
+use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
+
+/// Cells of the short bars meters draw in a status bar.
+pub const BAR: u8 = 10;
+
+/// Lays out the parts of a widget drawn on a single line of a status
+/// bar, a space apart and cut off at the end of the room it was given.
+pub struct Inline {
+    pos: ScreenPos,
+    width: u16,
+    gap: u16,
+    used: u16,
+}
+
+impl Inline {
+    pub fn new(pos: ScreenPos, width: u16) -> Self {
+        Self {
+            pos,
+            width,
+            gap: 1,
+            used: 0,
+        }
+    }
+
+    /// Put `gap` cells between the parts instead of a space.
+    pub fn spaced(mut self, gap: u16) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Claim `cells` after the previous part, `None` if they don't fit.
+    pub fn reserve(&mut self, cells: u16) -> Option<ScreenPos> {
+        let start = self.start();
+        if cells == 0 || start + cells > self.width {
+            return None;
+        }
+
+        self.used = start + cells;
+        Some(ScreenPos::new(self.pos.x + start, self.pos.y))
+    }
+
+    /// Draw `text` after the previous part, as much of it as fits.
+    pub fn text(
+        &mut self,
+        viewport: &mut Viewport,
+        text: &str,
+        (fg, bg): (Option<Color>, Option<Color>),
+    ) {
+        let start = self.start();
+        let text = text
+            .chars()
+            .take(self.width.saturating_sub(start) as usize)
+            .collect::<String>();
+
+        let cells = text.chars().count() as u16;
+        if let Some(pos) = self.reserve(cells) {
+            viewport.draw_widget(&Text::new(text, fg, bg), pos);
+        }
+    }
+
+    /// Let `draw` fill the room after the previous part, given where to
+    /// start and the cells left. It returns the cells it used.
+    pub fn widget(&mut self, draw: impl FnOnce(ScreenPos, u16) -> Result<u16>) -> Result<()> {
+        let start = self.start();
+        if start >= self.width {
+            return Ok(());
+        }
+
+        let pos = ScreenPos::new(self.pos.x + start, self.pos.y);
+        let cells = draw(pos, self.width - start)?;
+        if cells > 0 {
+            self.used = start + cells;
+        }
+        Ok(())
+    }
+
+    /// Cells taken up so far.
+    pub fn used(&self) -> u16 {
+        self.used
+    }
+
+    fn start(&self) -> u16 {
+        if self.used == 0 {
+            0
+        } else {
+            self.used + self.gap
+        }
+    }
+}
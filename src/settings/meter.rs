@@ -1,68 +1,95 @@
-use std::{process::Command, time::Instant};
-
 use anyhow::Result;
 use serde::Deserialize;
-use tinybit::{widgets::Text, ScreenPos, Viewport};
+use tinybit::{ScreenPos, Viewport};
 
+use crate::layout::Rect;
+use crate::popup::Popup;
+use crate::scheduler::Scheduler;
 use crate::MeterTheme;
 
-use super::{CommandExt, Widget};
+use super::header::Header;
+use super::inline::{self, Inline};
+use super::sizing::Sizing;
+use super::{Probe, Widget};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Meter {
     pub title: Option<String>,
     pub unit: Option<String>,
     pub prefix: Option<String>,
 
-    max_command: Vec<String>,
-    value_command: Vec<String>,
-    frequency: u64,
+    #[serde(flatten)]
+    pub probe: Probe,
 
+    #[serde(default)]
     pub right: bool,
+    #[serde(default)]
     pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+    #[serde(flatten)]
+    pub sizing: Sizing,
 
     pub meter: bool,
     pub reading: bool,
 
     #[serde(skip_deserializing)]
-    pub max_value: u64,
-    #[serde(skip_deserializing)]
-    pub current_value: u64,
+    pub selected: bool,
 
     #[serde(default)]
     pub theme: MeterTheme,
-
-    #[serde(skip_deserializing)]
-    max_cmd: Option<Command>,
-    #[serde(skip_deserializing)]
-    value_cmd: Option<Command>,
-    #[serde(skip_deserializing)]
-    timer: Option<Instant>,
 }
 
 impl Meter {
-    pub fn update(&mut self) -> Result<()> {
-        if self
-            .timer
-            .map(|t| t.elapsed().as_secs() > self.frequency)
-            .unwrap_or(true)
-        {
-            self.timer = Some(Instant::now());
-
-            if let Some(mut cmd) = super::construct_command(&self.value_command) {
-                self.current_value = cmd.get_stdout().parse()?;
-            }
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        self.probe.init(scheduler)
+    }
+
+    /// A disk meter without a `path` becomes one meter per mounted
+    /// filesystem, titled after its mount point.
+    pub fn discover(self) -> Vec<Meter> {
+        let probes = self.probe.discover();
+        if probes.is_empty() {
+            return vec![self];
         }
 
-        Ok(())
+        probes
+            .into_iter()
+            .map(|(mount, probe)| Meter {
+                title: Some(match &self.title {
+                    Some(title) => format!("{} {}", title, mount),
+                    None => mount,
+                }),
+                probe,
+                ..self.clone()
+            })
+            .collect()
     }
 
-    pub fn init(&mut self) -> Result<()> {
-        if let Some(mut cmd) = super::construct_command(&self.max_command) {
-            self.max_value = cmd.get_stdout().parse()?;
-        }
+    /// The label picked out of the output, or the title without one.
+    fn title(&self) -> Option<&str> {
+        self.probe.label.as_deref().or(self.title.as_deref())
+    }
 
-        Ok(())
+    /// Whether there is a line above the bar. Goes by the config rather
+    /// than the label, so the row count holds once the first label is in.
+    fn has_header(&self) -> bool {
+        self.reading || self.title.is_some() || self.probe.has_label()
+    }
+
+    fn header(&self) -> Header<'_> {
+        let ratio = self.probe.ratio(self.probe.current_value);
+        Header {
+            title: self.title(),
+            reading: self
+                .reading
+                .then(|| self.probe.reading(self.unit.as_deref())),
+            error: self.probe.error(),
+            colors: self
+                .theme
+                .text_colors(ratio, self.probe.stale, self.selected),
+            error_color: self.theme.colors.error_color,
+        }
     }
 
     pub fn set_theme(&mut self, theme: MeterTheme) {
@@ -83,17 +110,13 @@ impl Default for Meter {
         Self {
             title: Some("RAM".to_string()),
             unit: Some("mb".to_string()),
-            max_value: 0,
-            current_value: 0,
-            max_command: vec!["echo 16014".to_string()],
-            value_command: vec!["memcheck".to_string()],
-            frequency: 1,
-            timer: None,
-            value_cmd: super::construct_command(&["memcheck".to_string()]),
-            max_cmd: super::construct_command(&["echo 16000".to_string()]),
+            probe: Probe::default(),
+            selected: false,
             prefix: None,
             right: true,
             bottom: false,
+            region: None,
+            sizing: Sizing::default(),
             meter: true,
             reading: true,
             theme: MeterTheme::default(0),
@@ -102,65 +125,46 @@ impl Default for Meter {
 }
 
 impl Widget for Meter {
+    fn update(&mut self) -> Result<()> {
+        self.probe.update();
+        Ok(())
+    }
+
     fn update_and_draw(
         &mut self,
         viewport: &mut Viewport,
-        pos: &mut ScreenPos,
+        area: Rect,
         resized: &bool,
     ) -> Result<()> {
         self.update()?;
 
-        // Offset one up if bottom aligned
-        if self.reading || self.title.is_some() {
-            pos.y = if self.bottom { pos.y - 1 } else { pos.y };
-        }
-
-        if self.reading {
-            let unit = match &self.unit {
-                Some(u) => u.clone(),
-                None => "".to_string(),
-            };
-
-            let value_reading = Text::new(
-                format!("{}/{}{}", self.current_value, self.max_value, unit),
-                self.theme.fg_color,
-                None,
-            );
-
-            viewport.draw_widget(
-                &value_reading,
-                ScreenPos::new(
-                    // TODO: why 2?!?
-                    pos.x + (viewport.size.width / 2 - 2 - value_reading.0.len() as u16),
-                    pos.y,
-                ),
-            );
-        }
+        let (_, area) = self.sizing.apply(area);
+        let pos = area.pos();
 
         // if we have a title or reading offset bar by 1
-        let bar_offset = if self.title.is_some() || self.reading {
-            1_u16
-        } else {
-            0_u16
-        };
+        let bar_offset = self.has_header() as u16;
 
-        if let Some(t) = &self.title {
-            viewport.draw_widget(
-                &Text::new(t, self.theme.fg_color, None),
-                ScreenPos::new(pos.x, pos.y),
-            );
-        };
+        self.header().draw(viewport, area);
 
         if *resized {
-            self.theme.resize((viewport.size.width / 2 - 2) as u8)
+            self.theme.resize(area.width.min(u8::MAX as u16) as u8)
         };
 
-        self.theme.draw(
-            viewport,
-            self,
-            (self.current_value as f32, self.max_value as f32),
-            ScreenPos::new(pos.x, pos.y + bar_offset),
-        );
+        let bar_pos = ScreenPos::new(pos.x, pos.y + bar_offset);
+
+        // Without a title or reading the error takes the place of the bar
+        if self.probe.error().is_none() || bar_offset > 0 {
+            self.theme.draw(
+                viewport,
+                self,
+                (
+                    self.probe.current_value,
+                    self.probe.min_value,
+                    self.probe.max_value,
+                ),
+                bar_pos,
+            );
+        }
 
         Ok(())
     }
@@ -173,11 +177,52 @@ impl Widget for Meter {
         self.right
     }
 
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
     fn vertical_size(&self) -> u8 {
-        if self.reading || self.title.is_some() {
-            2
-        } else {
-            1
+        self.sizing.height(1 + self.has_header() as u8)
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        self.update()?;
+
+        self.theme.fit(self.prefix.as_deref(), inline::BAR);
+
+        let header = self.header();
+        let mut line = Inline::new(pos, width);
+        header.draw_title(viewport, &mut line);
+
+        if let Some(bar_pos) = line.reserve(self.theme.width() as u16) {
+            self.theme.draw(
+                viewport,
+                self,
+                (
+                    self.probe.current_value,
+                    self.probe.min_value,
+                    self.probe.max_value,
+                ),
+                bar_pos,
+            );
         }
+
+        header.draw_reading(viewport, &mut line);
+
+        Ok(line.used())
+    }
+
+    fn details(&self) -> Option<Popup> {
+        let title = self
+            .title
+            .as_deref()
+            .or(self.prefix.as_deref())
+            .unwrap_or("Meter");
+
+        Some(Popup::new(title.trim(), self.probe.details()))
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
     }
 }
@@ -6,22 +6,37 @@ use tinybit::Color;
 use tinybit::ScreenPos;
 use tinybit::Viewport;
 
+use super::colors::{self, Colors};
 use super::parse_ansi;
 use crate::settings::Meter;
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+/// A color taken on once the ratio of a meter passes `above`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Threshold {
+    above: f64,
+    fg: u8,
+
+    #[serde(skip_deserializing)]
+    color: Option<Color>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MeterTheme {
     start: Option<char>,
     end: Option<char>,
     meter: char,
     meter_bg: Option<char>,
 
-    fg: Option<u8>,
-    bg: Option<u8>,
+    #[serde(flatten)]
+    pub colors: Colors,
+
+    /// Recolor bar, reading and title as the ratio climbs.
     #[serde(default)]
-    pub fg_color: Option<Color>,
+    thresholds: Vec<Threshold>,
+    /// Color every cell of the bar by the threshold it sits past,
+    /// instead of the whole bar by the current ratio.
     #[serde(default)]
-    pub bg_color: Option<Color>,
+    gradient: bool,
 
     #[serde(skip_deserializing)]
     width: u8,
@@ -29,23 +44,120 @@ pub struct MeterTheme {
 
 impl MeterTheme {
     pub fn init(&mut self) {
-        self.fg_color = parse_ansi(self.fg);
-        self.bg_color = parse_ansi(self.bg);
+        self.colors.init();
+
+        self.thresholds.sort_by(|a, b| a.above.total_cmp(&b.above));
+        for threshold in self.thresholds.iter_mut() {
+            threshold.color = parse_ansi(Some(threshold.fg));
+        }
+    }
+
+    /// Foreground color of the highest threshold `ratio` is past,
+    /// `fg` below all of them or without a ratio.
+    pub fn level(&self, ratio: Option<f64>, stale: bool) -> Option<Color> {
+        let threshold = ratio.and_then(|ratio| {
+            self.thresholds
+                .iter()
+                .rev()
+                .find(|threshold| ratio > threshold.above)
+        });
+
+        match threshold {
+            Some(threshold) if !stale => threshold.color,
+            _ => self.colors.fg(stale),
+        }
+    }
+
+    /// Text colors for title and reading at `ratio`, inverted while selected.
+    pub fn text_colors(
+        &self,
+        ratio: Option<f64>,
+        stale: bool,
+        selected: bool,
+    ) -> (Option<Color>, Option<Color>) {
+        colors::text_colors(self.level(ratio, stale), selected)
     }
 
     pub fn draw(
         &self,
         viewport: &mut Viewport,
         meter: &Meter,
-        (current, max): (f32, f32),
+        (current, min, max): (f64, f64, f64),
         position: ScreenPos,
     ) {
-        let decoration_size = self.start.is_some() as u8 + self.end.is_some() as u8;
+        // An empty range (or a max not read yet) draws an empty bar
+        let ratio = (current - min) / (max - min);
+        let ratio = if ratio.is_finite() { ratio } else { 0.0 };
+        let bar = (meter.prefix.as_deref(), meter.probe.stale, meter.selected);
 
-        let prefix = match &meter.prefix {
-            Some(p) => p.to_string(),
-            _ => "".to_string(),
-        };
+        if !self.gradient {
+            let fg_color = self.level(Some(ratio), false);
+            return self.draw_segments(viewport, bar, &[(ratio, fg_color)], position);
+        }
+
+        let cells = self.gradient(ratio, self.bar_width(bar.0) as usize);
+        self.draw_cells(viewport, bar, &cells, position);
+    }
+
+    /// Every filled cell of a `bar_width` wide bar, in the color of
+    /// the level it sits at.
+    fn gradient(&self, ratio: f64, bar_width: usize) -> Vec<Option<Color>> {
+        let filled = (ratio.clamp(0.0, 1.0) * bar_width as f64) as usize;
+        (0..filled)
+            .map(|cell| self.level(Some((cell + 1) as f64 / bar_width as f64), false))
+            .collect()
+    }
+
+    /// Draw a bar made up of `segments`, each a share of the bar in its
+    /// own color, laid end to end.
+    pub fn draw_segments(
+        &self,
+        viewport: &mut Viewport,
+        bar: (Option<&str>, bool, bool),
+        segments: &[(f64, Option<Color>)],
+        position: ScreenPos,
+    ) {
+        let bar_width = self.bar_width(bar.0) as usize;
+
+        // Clamped so segments never run past the end
+        let mut filled = 0.0;
+        let mut cells = vec![];
+        for &(ratio, color) in segments {
+            filled = (filled + ratio.max(0.0)).min(1.0);
+            let end = (filled * bar_width as f64) as usize;
+            cells.resize(end.max(cells.len()), color);
+        }
+
+        self.draw_cells(viewport, bar, &cells, position);
+    }
+
+    /// Cells of the bar left for the meter itself, none if prefix and
+    /// decorations take up the whole width.
+    fn bar_width(&self, prefix: Option<&str>) -> u8 {
+        self.width.saturating_sub(self.decoration_size(prefix))
+    }
+
+    /// Cells taken by `prefix` and the start and end characters.
+    fn decoration_size(&self, prefix: Option<&str>) -> u8 {
+        let prefix = prefix.map_or(0, |p| p.len().min(u8::MAX as usize) as u8);
+        prefix.saturating_add(self.start.is_some() as u8 + self.end.is_some() as u8)
+    }
+
+    /// Draw the bar with one filled cell per color in `cells`.
+    fn draw_cells(
+        &self,
+        viewport: &mut Viewport,
+        (prefix, stale, selected): (Option<&str>, bool, bool),
+        cells: &[Option<Color>],
+        position: ScreenPos,
+    ) {
+        let fg_color = self.colors.fg(stale);
+        let bar_width = self.bar_width(prefix);
+        if bar_width == 0 {
+            return;
+        }
+
+        let prefix = prefix.unwrap_or("");
 
         let start = match self.start {
             Some(c) => c.to_string(),
@@ -57,54 +169,59 @@ impl MeterTheme {
             _ => "".to_string(),
         };
 
-        let bar_width = self.width - prefix.len() as u8 - decoration_size;
-
-        let progress =
-            current / max * (self.width as f32 - decoration_size as f32 - prefix.len() as f32);
-
-        let bar = iter::repeat(self.meter)
-            .take(progress as usize)
-            .collect::<String>();
-
-        let clear = iter::repeat(' ')
-            .take(bar_width as usize)
-            .collect::<String>();
+        let clear = " ".repeat(bar_width as usize);
 
         // draw background
         viewport.draw_widget(
             &Text::new(
                 format!("{}{}{}{}", prefix, start, clear, end),
-                self.fg_color,
+                fg_color,
                 None,
             ),
             position,
         );
 
+        if selected && !prefix.is_empty() {
+            let (fg_color, bg_color) = self.colors.text_colors(stale, true);
+            viewport.draw_widget(&Text::new(prefix, fg_color, bg_color), position);
+        }
+
+        let bar_x = position.x + self.start.is_some() as u16 + prefix.len() as u16;
+
         if let Some(c) = self.meter_bg {
             let bgbar = iter::repeat(c).take(bar_width as usize).collect::<String>();
             viewport.draw_widget(
-                &Text::new(bgbar, self.bg_color, None),
-                ScreenPos::new(
-                    position.x + self.start.is_some() as u16 + prefix.len() as u16,
-                    position.y,
-                ),
+                &Text::new(bgbar, self.colors.bg_color, None),
+                ScreenPos::new(bar_x, position.y),
             );
         }
 
-        // draw meter
-        viewport.draw_widget(
-            &Text::new(bar, self.fg_color, None),
-            ScreenPos::new(
-                position.x + self.start.is_some() as u16 + prefix.len() as u16,
-                position.y,
-            ),
-        );
+        // draw meter, one run of cells per color
+        let mut drawn = 0;
+        for run in cells.chunk_by(|a, b| a == b) {
+            let bar = iter::repeat(self.meter).take(run.len()).collect::<String>();
+            let color = if stale { fg_color } else { run[0] };
+            viewport.draw_widget(
+                &Text::new(bar, color, None),
+                ScreenPos::new(bar_x + drawn as u16, position.y),
+            );
+            drawn += run.len();
+        }
     }
 
     pub fn resize(&mut self, width: u8) {
         self.width = width;
     }
 
+    /// Resize to a bar of `cells` after `prefix` and the decorations.
+    pub fn fit(&mut self, prefix: Option<&str>, cells: u8) {
+        self.width = cells.saturating_add(self.decoration_size(prefix));
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
     pub fn default(width: u8) -> Self {
         Self {
             start: Some('['),
@@ -112,10 +229,9 @@ impl MeterTheme {
             meter: '=',
             width,
             meter_bg: Some('-'),
-            fg_color: None,
-            bg_color: None,
-            fg: Some(7),
-            bg: Some(245),
+            colors: Colors::new(Some(7), Some(245)),
+            thresholds: vec![],
+            gradient: false,
         }
     }
     pub fn halfblock(width: u8) -> Self {
@@ -125,10 +241,9 @@ impl MeterTheme {
             meter: '▀',
             width,
             meter_bg: Some('▀'),
-            fg_color: None,
-            bg_color: None,
-            fg: Some(0),
-            bg: Some(0),
+            colors: Colors::new(Some(0), Some(0)),
+            thresholds: vec![],
+            gradient: false,
         }
     }
 }
@@ -148,3 +263,77 @@ fn fg_color() -> Option<Color> {
 fn bg_color() -> Option<Color> {
     Some(Color::DarkGreen)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bar_width_leaves_room_for_prefix_and_decorations() {
+        let theme = MeterTheme::default(20);
+        assert_eq!(theme.bar_width(None), 18);
+        assert_eq!(theme.bar_width(Some("CPU ")), 14);
+        assert_eq!(MeterTheme::halfblock(20).bar_width(Some("CPU ")), 16);
+    }
+
+    #[test]
+    fn bar_width_is_zero_when_too_narrow() {
+        let mut theme = MeterTheme::default(0);
+        theme.resize(3);
+        assert_eq!(theme.bar_width(Some("CPU ")), 0);
+        assert_eq!(theme.bar_width(Some(&"x".repeat(300))), 0);
+    }
+
+    #[test]
+    fn fit_adds_prefix_and_decorations() {
+        let mut theme = MeterTheme::default(0);
+        theme.fit(Some("CPU "), 10);
+        assert_eq!(theme.width(), 16);
+        assert_eq!(theme.bar_width(Some("CPU ")), 10);
+
+        theme.fit(Some("CPU "), u8::MAX);
+        assert_eq!(theme.width(), u8::MAX);
+    }
+
+    fn themed(thresholds: &str) -> MeterTheme {
+        let mut theme: MeterTheme =
+            toml::from_str(&format!("meter = \"=\"\nfg = 100\n{}", thresholds)).unwrap();
+        theme.init();
+        theme
+    }
+
+    const FG: Option<Color> = Some(Color::AnsiValue(100));
+    const WARN: Option<Color> = Some(Color::AnsiValue(214));
+    const CRIT: Option<Color> = Some(Color::AnsiValue(196));
+
+    #[test]
+    fn level_is_the_highest_threshold_passed() {
+        // Out of order on purpose, `init` sorts them
+        let theme = themed("thresholds = [{ above = 0.9, fg = 196 }, { above = 0.7, fg = 214 }]");
+        assert_eq!(theme.level(Some(0.5), false), FG);
+        assert_eq!(theme.level(Some(0.8), false), WARN);
+        assert_eq!(theme.level(Some(0.95), false), CRIT);
+
+        // Only past a threshold, not at it
+        assert_eq!(theme.level(Some(0.7), false), FG);
+        assert_eq!(theme.level(Some(0.9), false), WARN);
+    }
+
+    #[test]
+    fn level_falls_back_to_fg() {
+        let theme = themed("thresholds = [{ above = 0.7, fg = 214 }]");
+        assert_eq!(theme.level(None, false), FG);
+        assert_eq!(theme.level(Some(0.95), true), Some(Color::AnsiValue(240)));
+        assert_eq!(themed("").level(Some(1.0), false), FG);
+    }
+
+    #[test]
+    fn gradient_colors_every_cell_by_its_level() {
+        let theme = themed("thresholds = [{ above = 0.5, fg = 214 }]");
+        assert_eq!(theme.gradient(1.0, 4), vec![FG, FG, WARN, WARN]);
+        assert_eq!(theme.gradient(0.6, 4), vec![FG, FG]);
+        assert_eq!(theme.gradient(2.0, 4).len(), 4);
+        assert!(theme.gradient(-1.0, 4).is_empty());
+        assert!(theme.gradient(1.0, 0).is_empty());
+    }
+}
@@ -1,17 +1,50 @@
+use std::collections::HashMap;
 use std::process::Command;
 
 use anyhow::{anyhow, Context, Result};
 use directories_next::ProjectDirs;
 use serde::Deserialize;
-use tinybit::{Color, ScreenPos, Viewport};
+use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
 
+use crate::layout::{Layout, Rect};
+use crate::popup::Popup;
+use crate::scheduler::Scheduler;
+
+pub use self::command::{CommandError, CommandExt, CommandLine, Diagnostics, Outcome};
+pub use self::extract::Extract;
+pub use self::format::Format;
+pub use self::inline::Inline;
 pub use self::meter::Meter;
-use self::{indicator::Indicator, separator::Separator};
+pub use self::probe::Probe;
+pub use self::source::{Sample, Source, SourceOptions};
+use self::{
+    container::{Container, Direction},
+    graph::Graph,
+    indicator::Indicator,
+    separator::Separator,
+    sparkline::Sparkline,
+    stack::Stack,
+};
 
+mod colors;
+mod command;
+mod container;
+mod extract;
+pub mod format;
+mod graph;
+mod header;
 mod indicator;
+mod inline;
 mod meter;
 pub mod meter_theme;
+mod probe;
+mod rate;
 mod separator;
+mod sizing;
+mod smoothing;
+mod source;
+mod sparkline;
+mod stack;
 
 pub fn load() -> Result<Conf> {
     let config_file = ProjectDirs::from("github", "the-gorg", "wonky")
@@ -22,13 +55,42 @@ pub fn load() -> Result<Conf> {
     let buf = std::fs::read(&config_file)
         .with_context(|| anyhow!("no config file found at: {}", config_file.display()))?;
 
-    toml::from_slice(&buf).map_err(Into::into)
+    parse(&buf)
 }
 
 pub fn load_at_path(path: &str) -> Result<Conf> {
-    let buf = std::fs::read(&path).with_context(|| anyhow!("no config file found at: {}", path))?;
+    let buf = std::fs::read(path).with_context(|| anyhow!("no config file found at: {}", path))?;
+
+    parse(&buf)
+}
+
+fn parse(buf: &[u8]) -> Result<Conf> {
+    let mut conf: Conf = toml::from_slice(buf)?;
+
+    if conf.settings.rotate == Some(0) {
+        return Err(anyhow!("rotate needs to be at least 1 second"));
+    }
+
+    // Widgets outside of `[[pages]]` make up the first page
+    if !conf.widgets.is_empty() || conf.pages.is_empty() {
+        let page = Page {
+            title: None,
+            widgets: std::mem::take(&mut conf.widgets),
+            layout: std::mem::take(&mut conf.layout),
+        };
+        conf.pages.insert(0, page);
+    }
+
+    for page in conf.pages.iter_mut() {
+        page.layout.validate()?;
 
-    toml::from_slice(&buf).map_err(Into::into)
+        page.widgets = std::mem::take(&mut page.widgets)
+            .into_iter()
+            .flat_map(Element::discover)
+            .collect();
+    }
+
+    Ok(conf)
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,62 +98,217 @@ pub struct Settings {
     pub bloatie: bool,
     #[serde(default)]
     pub single_row: bool,
+    /// Seconds before moving on to the next page by itself.
+    pub rotate: Option<u64>,
 }
 
 pub trait Widget {
+    /// Pick up new readings without drawing, so widgets on hidden
+    /// pages keep their history.
+    fn update(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     fn update_and_draw(
         &mut self,
         viewport: &mut Viewport,
-        pos: &mut ScreenPos,
+        area: Rect,
         resized: &bool,
     ) -> Result<()>;
     fn is_bottom(&self) -> bool;
     fn is_right(&self) -> bool;
+    /// Layout region to draw in, without one the corner picked by
+    /// `right` and `bottom`.
+    fn region(&self) -> Option<&str>;
     fn vertical_size(&self) -> u8;
+
+    /// Draw on a single line of a status bar, in at most `width` cells,
+    /// returns the cells used.
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16>;
+
+    /// Detail popup for the widget, `None` if there is nothing to inspect.
+    fn details(&self) -> Option<Popup> {
+        None
+    }
+
+    fn set_selected(&mut self, _selected: bool) {}
+
+    /// Widgets held inside this one, selected on their own.
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        vec![]
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum Element {
-    Meter(Meter),
-    Indicator(Indicator),
+    Meter(Box<Meter>),
+    Indicator(Box<Indicator>),
     Separator(Separator),
+    Sparkline(Box<Sparkline>),
+    Graph(Box<Graph>),
+    Stack(Box<Stack>),
+    Row(Box<Container>),
+    Column(Box<Container>),
+    Box(Box<Container>),
+}
+
+impl Element {
+    /// Start the jobs feeding the widget and set up its theme.
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        match self {
+            Element::Meter(m) => {
+                m.init(scheduler)?;
+                m.theme.init();
+            }
+            Element::Indicator(i) => {
+                i.init(scheduler)?;
+                i.theme.init();
+            }
+            Element::Separator(s) => s.theme.init(),
+            Element::Sparkline(s) => {
+                s.init(scheduler)?;
+                s.theme.init();
+            }
+            Element::Graph(g) => {
+                g.init(scheduler)?;
+                g.theme.init();
+            }
+            Element::Stack(s) => {
+                s.init(scheduler)?;
+                s.theme.init();
+            }
+            Element::Row(c) => c.init(scheduler, Direction::Row, false)?,
+            Element::Column(c) => c.init(scheduler, Direction::Column, false)?,
+            Element::Box(c) => c.init(scheduler, Direction::Column, true)?,
+        }
+        Ok(())
+    }
+
+    pub fn widget(&self) -> &dyn Widget {
+        match self {
+            Element::Meter(m) => m.as_ref(),
+            Element::Indicator(i) => i.as_ref(),
+            Element::Separator(s) => s,
+            Element::Sparkline(s) => s.as_ref(),
+            Element::Graph(g) => g.as_ref(),
+            Element::Stack(s) => s.as_ref(),
+            Element::Row(c) | Element::Column(c) | Element::Box(c) => c.as_ref(),
+        }
+    }
+
+    pub fn widget_mut(&mut self) -> &mut dyn Widget {
+        match self {
+            Element::Meter(m) => m.as_mut(),
+            Element::Indicator(i) => i.as_mut(),
+            Element::Separator(s) => s,
+            Element::Sparkline(s) => s.as_mut(),
+            Element::Graph(g) => g.as_mut(),
+            Element::Stack(s) => s.as_mut(),
+            Element::Row(c) | Element::Column(c) | Element::Box(c) => c.as_mut(),
+        }
+    }
+
+    /// Expand widgets that stand in for several, like a disk meter
+    /// without a path.
+    fn discover(self) -> Vec<Element> {
+        match self {
+            Element::Meter(m) => (*m)
+                .discover()
+                .into_iter()
+                .map(|m| Element::Meter(Box::new(m)))
+                .collect(),
+            Element::Row(mut c) => {
+                c.discover();
+                vec![Element::Row(c)]
+            }
+            Element::Column(mut c) => {
+                c.discover();
+                vec![Element::Column(c)]
+            }
+            Element::Box(mut c) => {
+                c.discover();
+                vec![Element::Box(c)]
+            }
+            element => vec![element],
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Conf {
+    #[serde(default)]
     pub widgets: Vec<Element>,
     pub settings: Settings,
+    #[serde(default)]
+    pub layout: Layout,
+    /// Every page, the widgets and layout above come first.
+    #[serde(default)]
+    pub pages: Vec<Page>,
 }
 
-pub trait CommandExt {
-    fn get_stdout(&mut self) -> String;
-}
-
-impl CommandExt for Command {
-    fn get_stdout(&mut self) -> String {
-        let output = self.output().expect("oops").stdout;
-
-        std::str::from_utf8(&output)
-            .expect("berp")
-            .trim()
-            .to_string()
-    }
+/// A screen full of widgets of its own, switched between with the
+/// number keys.
+#[derive(Deserialize)]
+pub struct Page {
+    pub title: Option<String>,
+    #[serde(default)]
+    pub widgets: Vec<Element>,
+    #[serde(default)]
+    pub layout: Layout,
 }
 
 //-------------------------------------------------------------------------------------
 // Common
 //-------------------------------------------------------------------------------------
 
-fn construct_command(command: &[String]) -> Option<Command> {
-    let mut iter = command.iter();
+fn construct_command(
+    command: &CommandLine,
+    env: &HashMap<String, String>,
+    cwd: Option<&str>,
+) -> Option<Command> {
+    let argv = command.argv(env);
+    let mut iter = argv.iter();
 
     let mut command = Command::new(iter.next()?);
-    command.args(iter);
+    command.args(iter).envs(env);
+
+    if let Some(dir) = cwd {
+        command.current_dir(command::expand(dir, env));
+    }
 
     Some(command)
 }
 
+/// Draw `ERR: <error>` right aligned on the `width` cells at `pos`, with
+/// a `!` in the last one marking the widget as failed. The details are
+/// in its popup.
+fn draw_error(
+    viewport: &mut Viewport,
+    error: &CommandError,
+    color: Option<Color>,
+    pos: ScreenPos,
+    width: u16,
+) {
+    let text = Text::new(format!("ERR: {}", error), color, None);
+    let end = width.saturating_sub(2);
+    viewport.draw_widget(
+        &text,
+        ScreenPos::new(
+            pos.x + end.saturating_sub(text.0.chars().count() as u16),
+            pos.y,
+        ),
+    );
+
+    viewport.draw_widget(
+        &Text::new("!", color, None),
+        ScreenPos::new(pos.x + width.saturating_sub(1), pos.y),
+    );
+}
+
+/// Colors for series and segments that don't pick one.
+const PALETTE: [u8; 6] = [10, 12, 11, 13, 14, 9];
+
 fn parse_ansi(color_number: Option<u8>) -> Option<Color> {
     match color_number {
         Some(n) => Color::parse_ansi(&("5;".to_string() + &n.to_string())[..]),
@@ -99,6 +316,16 @@ fn parse_ansi(color_number: Option<u8>) -> Option<Color> {
     }
 }
 
+#[allow(clippy::unnecessary_wraps)]
+fn stale_fg() -> Option<u8> {
+    Some(240)
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn error_fg() -> Option<u8> {
+    Some(196)
+}
+
 #[allow(dead_code, clippy::unnecessary_wraps)]
 fn fg_color() -> Option<Color> {
     Some(Color::Green)
@@ -108,3 +335,91 @@ fn fg_color() -> Option<Color> {
 fn bg_color() -> Option<Color> {
     Some(Color::DarkGreen)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> Result<Conf> {
+        parse(format!("{}\n[settings]\nbloatie = false\n", toml).as_bytes())
+    }
+
+    #[test]
+    fn top_level_widgets_are_the_first_page() {
+        let conf = config(
+            r#"
+            [[widgets]]
+            type = "Separator"
+
+            [layout]
+            rows = ["1fr"]
+            columns = ["1fr", "1fr"]
+            regions = [{ name = "side", column = 1 }]
+
+            [[pages]]
+            title = "net"
+
+            [[pages.widgets]]
+            type = "Separator"
+
+            [[pages.widgets]]
+            type = "Separator"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(conf.pages.len(), 2);
+        assert_eq!(conf.pages[0].title, None);
+        assert_eq!(conf.pages[0].widgets.len(), 1);
+        assert!(conf.pages[0].layout.find("side").is_ok());
+        assert_eq!(conf.pages[1].title.as_deref(), Some("net"));
+        assert_eq!(conf.pages[1].widgets.len(), 2);
+        assert!(conf.pages[1].layout.find("side").is_err());
+    }
+
+    #[test]
+    fn pages_alone_or_no_widgets_at_all() {
+        let conf = config("[[pages]]\ntitle = \"net\"").unwrap();
+        assert_eq!(conf.pages.len(), 1);
+        assert_eq!(conf.pages[0].title.as_deref(), Some("net"));
+
+        let conf = config("").unwrap();
+        assert_eq!(conf.pages.len(), 1);
+        assert!(conf.pages[0].widgets.is_empty());
+    }
+
+    #[test]
+    fn page_layouts_are_validated() {
+        let layout = r#"
+            rows = ["1fr"]
+            columns = ["1fr", "1fr"]
+            regions = [{ name = "side", column = 2 }]
+            "#;
+
+        assert!(config(&format!("[layout]\n{}", layout)).is_err());
+        assert!(config(&format!("[[pages]]\n[pages.layout]\n{}", layout)).is_err());
+    }
+
+    #[test]
+    fn rotate_needs_a_second() {
+        let settings = |rotate| format!("[settings]\nbloatie = false\nrotate = {}", rotate);
+        assert!(parse(settings(0).as_bytes()).is_err());
+
+        let conf = parse(settings(5).as_bytes()).unwrap();
+        assert_eq!(conf.settings.rotate, Some(5));
+    }
+
+    #[test]
+    fn disks_are_discovered_on_every_page() {
+        let disk = "type = \"Meter\"\nsource = \"disk\"\nmeter = true\nreading = true";
+        let conf = config(&format!(
+            "[[widgets]]\n{}\n[[pages]]\n[[pages.widgets]]\n{}",
+            disk, disk
+        ))
+        .unwrap();
+
+        let disks = source::mounts().len().max(1);
+        assert_eq!(conf.pages[0].widgets.len(), disks);
+        assert_eq!(conf.pages[1].widgets.len(), disks);
+    }
+}
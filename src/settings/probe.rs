@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::scheduler::{Latest, Scheduler};
+
+use super::command::{self, CommandError, CommandExt, CommandLine, Diagnostics, Outcome};
+use super::format::{self, Format};
+use super::rate::{Counter, Mode};
+use super::smoothing::Smoothing;
+use super::{Extract, Sample, Source, SourceOptions};
+
+/// Where a widget's numbers come from: commands or a built-in source,
+/// along with extraction, rate and smoothing. Shared by every widget
+/// that draws a value against its range.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Probe {
+    /// Prints value and max together, both refreshed on every run.
+    command: Option<CommandLine>,
+    min_command: Option<CommandLine>,
+    max_command: Option<CommandLine>,
+    value_command: Option<CommandLine>,
+    source: Option<Source>,
+    #[serde(flatten)]
+    source_options: SourceOptions,
+    #[serde(flatten)]
+    extract: Extract,
+    /// `rate` shows how fast a counter grows per second.
+    #[serde(default)]
+    mode: Mode,
+    smoothing: Option<Smoothing>,
+    #[serde(default)]
+    frequency: u64,
+    timeout: Option<u64>,
+    #[serde(default)]
+    stream: bool,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    cwd: Option<String>,
+
+    /// Scale readings to bytes, durations and the like, see `Format`.
+    format: Option<Format>,
+    /// Decimals shown in the reading, overrides the default of `format`.
+    decimals: Option<usize>,
+
+    #[serde(default)]
+    pub min_value: f64,
+    #[serde(skip_deserializing)]
+    pub max_value: f64,
+    #[serde(skip_deserializing)]
+    pub current_value: f64,
+    /// The value before smoothing, for the detail popup.
+    #[serde(skip_deserializing)]
+    raw_value: f64,
+    /// Every value of the last reading when reading several,
+    /// `current_value` is their sum.
+    #[serde(skip_deserializing)]
+    pub values: Vec<f64>,
+    #[serde(skip_deserializing)]
+    several: bool,
+    /// Picked out of the output by an extractor, drawn instead of the title.
+    #[serde(skip_deserializing)]
+    pub label: Option<String>,
+    #[serde(skip_deserializing)]
+    pub stale: bool,
+    #[serde(skip_deserializing)]
+    error: Option<CommandError>,
+    #[serde(skip_deserializing)]
+    counters: Vec<Counter>,
+    #[serde(skip_deserializing)]
+    smoothers: Vec<Smoothing>,
+    /// Of the last value command run or the source.
+    #[serde(skip_deserializing)]
+    diagnostics: Diagnostics,
+
+    #[serde(skip_deserializing)]
+    min: Bound,
+    #[serde(skip_deserializing)]
+    max: Bound,
+    #[serde(skip_deserializing)]
+    value_output: Option<Latest<Outcome>>,
+    #[serde(skip_deserializing)]
+    source_output: Option<Latest<Result<Sample, CommandError>>>,
+}
+
+impl Probe {
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        let timeout = self.timeout.map(Duration::from_secs);
+        self.extract.init()?;
+
+        if let Some(mut cmd) = self.command(&self.min_command) {
+            self.min.output = Some(scheduler.once(move || cmd.run(timeout)));
+        }
+
+        if let Some(mut cmd) = self.command(&self.max_command) {
+            self.max.output = Some(scheduler.once(move || cmd.run(timeout)));
+        }
+
+        if let Some(source) = self.source {
+            self.format = self.format.or(source.format());
+            let sampler = source.sampler(&self.source_options);
+            self.diagnostics.command = source.to_string();
+            self.source_output = Some(scheduler.every(self.frequency, sampler));
+        } else if let Some(mut cmd) = self
+            .command(&self.command)
+            .or_else(|| self.command(&self.value_command))
+        {
+            self.value_output = Some(if self.stream {
+                scheduler.stream(move |publish| cmd.stream(publish))
+            } else {
+                scheduler.every(self.frequency, move || cmd.run(timeout))
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Read every number in the output instead of a single one,
+    /// for widgets that draw several values at once.
+    pub fn read_several(&mut self) {
+        self.several = true;
+    }
+
+    /// Pick up whatever the jobs published since the last call,
+    /// returns when the new value was published if one came in.
+    pub fn update(&mut self) -> Option<Instant> {
+        if let Some(min) = self.min.take().and_then(|min| self.bound(min)) {
+            self.min_value = min;
+        }
+
+        if let Some(max) = self.max.take().and_then(|max| self.bound(max)) {
+            self.max_value = max;
+        }
+
+        let mut updated = None;
+
+        if let Some(((output, diagnostics), at)) =
+            self.value_output.as_ref().and_then(Latest::take_stamped)
+        {
+            self.diagnostics = diagnostics;
+            let value = output.and_then(|o| self.read_output(&o));
+            if self.receive(value, at) {
+                updated = Some(at);
+            }
+        }
+
+        if let Some((sample, at)) = self.source_output.as_ref().and_then(Latest::take_stamped) {
+            if let Some(max) = sample.as_ref().ok().and_then(|s| s.max) {
+                self.max_value = max;
+            }
+            if self.receive(sample.map(|s| vec![s.value]), at) {
+                updated = Some(at);
+            }
+        }
+
+        updated
+    }
+
+    /// A min or max that came in, a timeout leaves the old one stale.
+    fn bound(&mut self, bound: Result<f64, CommandError>) -> Option<f64> {
+        match bound {
+            Ok(bound) => Some(bound),
+            Err(CommandError::TimedOut) => {
+                self.stale = true;
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// Run the extractors over value command output, picking up
+    /// max and label along the way.
+    fn read_output(&mut self, output: &str) -> Result<Vec<f64>, CommandError> {
+        // Reading several, every number is a value of its own
+        let extracted = if self.command.is_some() && !self.extract.is_set() && !self.several {
+            super::extract::value_and_max(output)?
+        } else {
+            self.extract.apply(output)?
+        };
+
+        if let Some(max) = extracted.max {
+            self.max_value = command::parse(&max)?;
+        }
+        if let Some(label) = extracted.label {
+            self.label = Some(label);
+        }
+
+        let value = extracted
+            .value
+            .ok_or_else(|| CommandError::Extract("no value extracted".to_string()))?;
+
+        if !self.several {
+            return command::parse(&value).map(|value| vec![value]);
+        }
+
+        value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .map(command::parse)
+            .collect()
+    }
+
+    /// Keep the last good value around, but mark it stale until
+    /// a fresh reading arrives. Returns whether the value changed.
+    fn receive(&mut self, values: Result<Vec<f64>, CommandError>, at: Instant) -> bool {
+        match values {
+            Ok(values) => {
+                self.stale = false;
+                self.error = None;
+
+                // Every value is a counter and smoothed on its own
+                self.counters.resize_with(values.len(), Counter::default);
+                if let Some(smoothing) = &self.smoothing {
+                    self.smoothers.resize(values.len(), smoothing.clone());
+                }
+
+                // A rate needs two samples, keep the old one until then.
+                // Every counter takes the sample before any is left out.
+                let values = match self.mode {
+                    Mode::Value => Some(values),
+                    Mode::Rate => values
+                        .iter()
+                        .zip(self.counters.iter_mut())
+                        .map(|(&value, counter)| counter.rate(value, at))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                        .collect(),
+                };
+                let values = match values {
+                    Some(values) => values,
+                    None => return false,
+                };
+
+                self.raw_value = values.iter().sum();
+                self.values = match self.smoothing {
+                    Some(_) => values
+                        .iter()
+                        .zip(self.smoothers.iter_mut())
+                        .map(|(&value, smoothing)| smoothing.apply(value, at))
+                        .collect(),
+                    None => values,
+                };
+                self.current_value = self.values.iter().sum();
+                true
+            }
+            Err(CommandError::TimedOut) => {
+                self.stale = true;
+                false
+            }
+            Err(e) => {
+                self.error = Some(e);
+                false
+            }
+        }
+    }
+
+    /// Whether the extractors pick out a label, see `Extract::has_label`.
+    pub fn has_label(&self) -> bool {
+        self.extract.has_label()
+    }
+
+    /// The error to show in place of the reading, if any.
+    pub fn error(&self) -> Option<&CommandError> {
+        self.failed().0
+    }
+
+    /// The error along with the run it came from, a failing min or max
+    /// command before the value.
+    fn failed(&self) -> (Option<&CommandError>, &Diagnostics) {
+        let bound = if self.min.error.is_some() {
+            &self.min
+        } else {
+            &self.max
+        };
+
+        match &bound.error {
+            Some(e) => (Some(e), &bound.diagnostics),
+            None => (self.error.as_ref(), &self.diagnostics),
+        }
+    }
+
+    /// Where `value` sits between min and max, `None` for an empty range.
+    pub fn ratio(&self, value: f64) -> Option<f64> {
+        let ratio = (value - self.min_value) / (self.max_value - self.min_value);
+        Some(ratio).filter(|r| r.is_finite())
+    }
+
+    /// A single number, written out according to `format` and `decimals`.
+    pub fn number(&self, value: f64) -> String {
+        match self.format {
+            Some(format) => format.apply(value, self.decimals),
+            None => format::number(value, self.decimals),
+        }
+    }
+
+    /// Current and max, or the percentage of the range, followed by `unit`.
+    pub fn reading(&self, unit: Option<&str>) -> String {
+        let unit = unit.unwrap_or("");
+
+        match self.format {
+            Some(Format::Percent) => {
+                let percent = self.ratio(self.current_value).unwrap_or(0.0) * 100.0;
+                format!("{}{}", Format::Percent.apply(percent, self.decimals), unit)
+            }
+            _ => format!(
+                "{}/{}{}",
+                self.number(self.current_value),
+                self.number(self.max_value),
+                unit
+            ),
+        }
+    }
+
+    /// Lines for the detail popup.
+    pub fn details(&self) -> Vec<String> {
+        let (error, diagnostics) = self.failed();
+        let mut lines = diagnostics.lines(error);
+
+        if self.smoothing.is_some() {
+            lines.insert(
+                0,
+                format!("raw:     {}", format::number(self.raw_value, None)),
+            );
+            lines.insert(
+                1,
+                format!("shown:   {}", format::number(self.current_value, None)),
+            );
+        }
+
+        lines
+    }
+
+    /// A disk probe without a `path` stands in for one probe per mounted
+    /// filesystem, returned along with their mount points.
+    pub fn discover(&self) -> Vec<(String, Probe)> {
+        if self.source != Some(Source::Disk) || self.source_options.path.is_some() {
+            return vec![];
+        }
+
+        super::source::mounts()
+            .into_iter()
+            .map(|mount| {
+                let mut probe = self.clone();
+                probe.source_options.path = Some(mount.clone());
+                (mount, probe)
+            })
+            .collect()
+    }
+
+    fn command(&self, command: &Option<CommandLine>) -> Option<Command> {
+        super::construct_command(command.as_ref()?, &self.env, self.cwd.as_deref())
+    }
+}
+
+/// A min or max command, run once, and how that went.
+#[derive(Debug, Clone, Default)]
+struct Bound {
+    output: Option<Latest<Outcome>>,
+    error: Option<CommandError>,
+    diagnostics: Diagnostics,
+}
+
+impl Bound {
+    /// Parse the outcome, if one arrived. A timeout keeps the last error.
+    fn take(&mut self) -> Option<Result<f64, CommandError>> {
+        let (output, diagnostics) = self.output.as_ref()?.take()?;
+        self.diagnostics = diagnostics;
+
+        let bound = output.and_then(|o| command::parse(&o));
+        match &bound {
+            Ok(_) => self.error = None,
+            Err(CommandError::TimedOut) => {}
+            Err(e) => self.error = Some(e.clone()),
+        }
+        Some(bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receive_keeps_the_last_good_values() {
+        let mut probe = Probe::default();
+        let now = Instant::now();
+
+        assert!(probe.receive(Ok(vec![1.0, 2.0]), now));
+        assert_eq!(probe.values, vec![1.0, 2.0]);
+        assert_eq!(probe.current_value, 3.0);
+
+        assert!(!probe.receive(Err(CommandError::Exit(Some(1))), now));
+        assert_eq!(probe.values, vec![1.0, 2.0]);
+        assert_eq!(probe.current_value, 3.0);
+        assert!(matches!(probe.error(), Some(CommandError::Exit(Some(1)))));
+        assert!(!probe.stale);
+
+        // A timeout only marks the values stale
+        assert!(!probe.receive(Err(CommandError::TimedOut), now));
+        assert_eq!(probe.current_value, 3.0);
+        assert!(probe.stale);
+
+        assert!(probe.receive(Ok(vec![5.0]), now));
+        assert_eq!(probe.current_value, 5.0);
+        assert!(probe.error().is_none());
+        assert!(!probe.stale);
+    }
+}
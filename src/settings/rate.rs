@@ -0,0 +1,113 @@
+use std::time::Instant;
+
+use serde::Deserialize;
+
+/// What a meter shows of the numbers it is fed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// The number itself.
+    #[default]
+    Value,
+    /// How fast the number grows per second, for ever increasing counters.
+    Rate,
+}
+
+/// Turns successive samples of a counter into a per second rate.
+#[derive(Debug, Clone, Default)]
+pub struct Counter {
+    previous: Option<(f64, Instant)>,
+}
+
+impl Counter {
+    /// Rate since the previous sample, `None` for the first sample
+    /// and after a reset.
+    pub fn rate(&mut self, value: f64, at: Instant) -> Option<f64> {
+        let (last, last_at) = self.previous.replace((value, at))?;
+
+        let seconds = at.saturating_duration_since(last_at).as_secs_f64();
+        if seconds <= 0.0 {
+            return None;
+        }
+
+        let delta = if value >= last {
+            value - last
+        } else {
+            wrapped(last, value)?
+        };
+
+        Some(delta / seconds)
+    }
+}
+
+/// A counter that went down either wrapped around at 32 or 64 bits, or was
+/// reset (a reboot, a restarted service) which leaves nothing to compare.
+/// Only a counter that was past half its range is taken to have wrapped.
+fn wrapped(last: f64, value: f64) -> Option<f64> {
+    [u32::MAX as f64, u64::MAX as f64]
+        .iter()
+        .find(|&&max| last <= max && last > max / 2.0)
+        .map(|max| max - last + value + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_per_second_from_the_second_sample() {
+        let start = Instant::now();
+        let mut counter = Counter::default();
+
+        assert_eq!(counter.rate(100.0, start), None);
+        assert_eq!(
+            counter.rate(300.0, start + Duration::from_secs(2)),
+            Some(100.0)
+        );
+        assert_eq!(
+            counter.rate(350.0, start + Duration::from_millis(2500)),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn no_rate_without_time_passing() {
+        let start = Instant::now();
+        let mut counter = Counter::default();
+
+        counter.rate(100.0, start);
+        assert_eq!(counter.rate(200.0, start), None);
+    }
+
+    #[test]
+    fn counters_wrap_at_32_and_64_bits() {
+        let start = Instant::now();
+        let mut counter = Counter::default();
+
+        counter.rate(u32::MAX as f64 - 9.0, start);
+        assert_eq!(
+            counter.rate(10.0, start + Duration::from_secs(1)),
+            Some(20.0)
+        );
+
+        let mut counter = Counter::default();
+        let max = u64::MAX as f64;
+        counter.rate(max - 1e6, start);
+        let rate = counter.rate(0.0, start + Duration::from_secs(1)).unwrap();
+        assert!(rate > 0.0 && rate <= 1e6 + 1.0);
+    }
+
+    #[test]
+    fn reset_counters_start_over() {
+        let start = Instant::now();
+        let mut counter = Counter::default();
+
+        counter.rate(1000.0, start);
+        assert_eq!(counter.rate(5.0, start + Duration::from_secs(1)), None);
+        assert_eq!(
+            counter.rate(15.0, start + Duration::from_secs(2)),
+            Some(10.0)
+        );
+    }
+}
@@ -2,8 +2,13 @@ use anyhow::Result;
 use serde::Deserialize;
 use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
 
+use crate::layout::Rect;
+
+use super::inline::Inline;
+use super::sizing::Sizing;
 use super::{parse_ansi, Widget};
 
+/// Plain text colors, also used for container borders.
 #[derive(Debug, Deserialize)]
 pub struct SeperatorTheme {
     fg: Option<u8>,
@@ -34,8 +39,14 @@ impl Default for SeperatorTheme {
 #[derive(Debug, Deserialize)]
 pub struct Separator {
     pub title: Option<String>,
+    #[serde(default)]
     pub right: bool,
+    #[serde(default)]
     pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+    #[serde(flatten)]
+    pub sizing: Sizing,
 
     #[serde(default)]
     pub theme: SeperatorTheme,
@@ -52,6 +63,8 @@ impl Default for Separator {
             title: None,
             right: false,
             bottom: false,
+            region: None,
+            sizing: Sizing::default(),
             theme: SeperatorTheme {
                 fg: Some(7),
                 bg: Some(245),
@@ -65,13 +78,22 @@ impl Widget for Separator {
     fn update_and_draw(
         &mut self,
         viewport: &mut Viewport,
-        pos: &mut ScreenPos,
+        area: Rect,
         _resized: &bool,
     ) -> Result<()> {
+        let (_, inner) = self.sizing.apply(area);
+
         if let Some(t) = &self.title {
+            let title = t.chars().take(inner.width as usize).collect::<String>();
+            let x = inner.x
+                + self
+                    .sizing
+                    .align
+                    .offset(title.chars().count() as u16, inner.width);
+
             viewport.draw_widget(
-                &Text::new(t, self.theme.fg_color, self.theme.bg_color),
-                ScreenPos::new(pos.x, pos.y),
+                &Text::new(title, self.theme.fg_color, self.theme.bg_color),
+                ScreenPos::new(x, inner.y),
             );
         }
 
@@ -86,7 +108,20 @@ impl Widget for Separator {
         self.right
     }
 
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
     fn vertical_size(&self) -> u8 {
-        1
+        self.sizing.height(1)
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        let mut line = Inline::new(pos, width);
+        if let Some(t) = &self.title {
+            line.text(viewport, t, (self.theme.fg_color, self.theme.bg_color));
+        }
+
+        Ok(line.used())
     }
 }
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+
+use crate::layout::Rect;
+
+/// Where a widget narrower than its area sits, and where the text of
+/// single line widgets sits inside them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    /// Offset of something `cells` wide in `room` cells.
+    pub fn offset(self, cells: u16, room: u16) -> u16 {
+        let free = room.saturating_sub(cells);
+        match self {
+            Align::Left => 0,
+            Align::Center => free / 2,
+            Align::Right => free,
+        }
+    }
+}
+
+/// The space a widget asks for in the area the layout hands it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Sizing {
+    /// Cells wide, the whole area if left out.
+    width: Option<u16>,
+    min_width: Option<u16>,
+    max_width: Option<u16>,
+    /// Rows taken at least, the rows the widget doesn't draw stay empty.
+    height: Option<u8>,
+    /// Cells kept clear inside the widget, left and right.
+    #[serde(default)]
+    padding: u16,
+    /// Cells kept clear around the widget, left and right.
+    #[serde(default)]
+    margin: u16,
+    #[serde(default)]
+    pub align: Align,
+}
+
+impl Sizing {
+    /// The part of `area` the widget takes up, and the part of that
+    /// left for its content after padding.
+    pub fn apply(&self, area: Rect) -> (Rect, Rect) {
+        let room = area.width.saturating_sub(self.margin.saturating_mul(2));
+
+        let width = self.width.unwrap_or(room);
+        let width = self.min_width.map_or(width, |min| width.max(min));
+        let width = self.max_width.map_or(width, |max| width.min(max));
+        let width = width.min(room);
+
+        let x = area.x + self.margin.min(area.width) + self.align.offset(width, room);
+        let outer = Rect::new(x, area.y, width, area.height);
+
+        let padding = self.padding.min(width / 2);
+        let inner = Rect::new(x + padding, area.y, width - padding * 2, area.height);
+
+        (outer, inner)
+    }
+
+    /// Rows taken by a widget that needs `rows` itself.
+    pub fn height(&self, rows: u8) -> u8 {
+        self.height.map_or(rows, |height| height.max(rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizing(toml: &str) -> Sizing {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn takes_the_whole_area_by_default() {
+        let area = Rect::new(2, 1, 40, 2);
+        assert_eq!(Sizing::default().apply(area), (area, area));
+    }
+
+    #[test]
+    fn clamps_width_and_aligns() {
+        let area = Rect::new(0, 0, 40, 1);
+        let (outer, _) = sizing("width = 50\nalign = \"right\"").apply(area);
+        assert_eq!(outer, Rect::new(0, 0, 40, 1));
+
+        let (outer, _) = sizing("width = 5\nmin_width = 10\nalign = \"center\"").apply(area);
+        assert_eq!(outer, Rect::new(15, 0, 10, 1));
+
+        let (outer, _) = sizing("max_width = 8\nalign = \"right\"").apply(area);
+        assert_eq!(outer, Rect::new(32, 0, 8, 1));
+    }
+
+    #[test]
+    fn margin_and_padding() {
+        let area = Rect::new(10, 0, 40, 1);
+        let (outer, inner) = sizing("margin = 2\npadding = 3").apply(area);
+        assert_eq!(outer, Rect::new(12, 0, 36, 1));
+        assert_eq!(inner, Rect::new(15, 0, 30, 1));
+    }
+
+    #[test]
+    fn oversized_margin_and_padding_leave_nothing() {
+        let area = Rect::new(0, 0, 10, 1);
+        let (outer, inner) = sizing("margin = 40000\npadding = 40000").apply(area);
+        assert_eq!(outer.width, 0);
+        assert_eq!(inner.width, 0);
+        assert!(outer.x <= area.x + area.width);
+    }
+
+    #[test]
+    fn height_is_a_minimum() {
+        assert_eq!(sizing("height = 3").height(2), 3);
+        assert_eq!(sizing("height = 1").height(2), 2);
+        assert_eq!(Sizing::default().height(2), 2);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+/// Samples kept by the rolling methods unless `samples` or `window` say otherwise.
+const DEFAULT_SAMPLES: usize = 10;
+
+/// How a meter smooths out spiky values before drawing them:
+///
+/// ```toml
+/// smoothing = { method = "ema", alpha = 0.3 }
+/// smoothing = { method = "percentile", percentile = 95, window = 60 }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Smoothing {
+    method: Method,
+    /// Weight of the newest sample for `ema`, between 0 and 1.
+    #[serde(default = "default_alpha")]
+    alpha: f64,
+    /// Number of samples the rolling methods look back over.
+    samples: Option<usize>,
+    /// Seconds the rolling methods look back over.
+    window: Option<u64>,
+    #[serde(default = "default_percentile")]
+    percentile: f64,
+
+    #[serde(skip)]
+    history: VecDeque<(f64, Instant)>,
+    #[serde(skip)]
+    average: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    /// Exponential moving average.
+    Ema,
+    Mean,
+    Max,
+    Min,
+    Percentile,
+}
+
+impl Smoothing {
+    /// Add a raw sample taken at `at`, returning the value to show.
+    pub fn apply(&mut self, value: f64, at: Instant) -> f64 {
+        if self.method != Method::Ema {
+            return self.rolling(value, at);
+        }
+
+        let average = match self.average {
+            Some(average) => average + self.alpha.clamp(0.0, 1.0) * (value - average),
+            None => value,
+        };
+        self.average = Some(average);
+        average
+    }
+
+    fn rolling(&mut self, value: f64, at: Instant) -> f64 {
+        self.history.push_back((value, at));
+
+        let samples = match (self.samples, self.window) {
+            (Some(samples), _) => samples.max(1),
+            (None, Some(_)) => usize::MAX,
+            (None, None) => DEFAULT_SAMPLES,
+        };
+        while self.history.len() > samples {
+            self.history.pop_front();
+        }
+
+        // Always keep the newest sample, however long it took to arrive
+        if let Some(window) = self.window.map(Duration::from_secs) {
+            while self.history.len() > 1
+                && self
+                    .history
+                    .front()
+                    .is_some_and(|&(_, oldest)| at.saturating_duration_since(oldest) > window)
+            {
+                self.history.pop_front();
+            }
+        }
+
+        let values = self.history.iter().map(|&(value, _)| value);
+
+        match self.method {
+            Method::Max => values.fold(f64::MIN, f64::max),
+            Method::Min => values.fold(f64::MAX, f64::min),
+            Method::Percentile => {
+                let mut sorted = values.collect::<Vec<_>>();
+                sorted.sort_by(f64::total_cmp);
+
+                // Nearest rank
+                let rank = (self.percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil();
+                sorted[(rank as usize).clamp(1, sorted.len()) - 1]
+            }
+            _ => values.sum::<f64>() / self.history.len() as f64,
+        }
+    }
+}
+
+fn default_alpha() -> f64 {
+    0.3
+}
+
+fn default_percentile() -> f64 {
+    95.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smoothing(toml: &str) -> Smoothing {
+        toml::from_str(toml).unwrap()
+    }
+
+    /// Feed `values` one second apart, returning what is shown after each.
+    fn feed(smoothing: &mut Smoothing, values: &[f64]) -> Vec<f64> {
+        let start = Instant::now();
+        values
+            .iter()
+            .enumerate()
+            .map(|(n, &value)| smoothing.apply(value, start + Duration::from_secs(n as u64)))
+            .collect()
+    }
+
+    #[test]
+    fn ema_starts_at_the_first_sample() {
+        let mut ema = smoothing("method = \"ema\"\nalpha = 0.5");
+        assert_eq!(feed(&mut ema, &[10.0, 20.0, 20.0]), vec![10.0, 15.0, 17.5]);
+
+        let mut clamped = smoothing("method = \"ema\"\nalpha = 3.0");
+        assert_eq!(feed(&mut clamped, &[10.0, 20.0]), vec![10.0, 20.0]);
+    }
+
+    #[test]
+    fn rolling_methods_look_back_over_samples() {
+        let values = [4.0, 8.0, 0.0, 2.0];
+
+        let mut mean = smoothing("method = \"mean\"\nsamples = 2");
+        assert_eq!(feed(&mut mean, &values), vec![4.0, 6.0, 4.0, 1.0]);
+
+        let mut max = smoothing("method = \"max\"\nsamples = 3");
+        assert_eq!(feed(&mut max, &values), vec![4.0, 8.0, 8.0, 8.0]);
+
+        let mut min = smoothing("method = \"min\"\nsamples = 2");
+        assert_eq!(feed(&mut min, &values), vec![4.0, 4.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn default_samples() {
+        let mut mean = smoothing("method = \"mean\"");
+        let values = (0..=DEFAULT_SAMPLES).map(|n| n as f64).collect::<Vec<_>>();
+        let shown = feed(&mut mean, &values);
+        // The first sample has dropped out by the last one
+        assert_eq!(shown[DEFAULT_SAMPLES], 5.5);
+    }
+
+    #[test]
+    fn window_drops_old_samples_but_keeps_the_newest() {
+        let mut max = smoothing("method = \"max\"\nwindow = 2");
+        assert_eq!(
+            feed(&mut max, &[9.0, 1.0, 2.0, 3.0]),
+            vec![9.0, 9.0, 9.0, 3.0]
+        );
+
+        let start = Instant::now();
+        let mut max = smoothing("method = \"max\"\nwindow = 2");
+        max.apply(9.0, start);
+        assert_eq!(max.apply(1.0, start + Duration::from_secs(60)), 1.0);
+    }
+
+    #[test]
+    fn percentile_is_nearest_rank() {
+        let mut p = smoothing("method = \"percentile\"\npercentile = 50\nsamples = 4");
+        assert_eq!(
+            feed(&mut p, &[4.0, 1.0, 3.0, 2.0]),
+            vec![4.0, 1.0, 3.0, 2.0]
+        );
+
+        let mut p = smoothing("method = \"percentile\"\nsamples = 20");
+        let values = (1..=20).map(|n| n as f64).collect::<Vec<_>>();
+        assert_eq!(*feed(&mut p, &values).last().unwrap(), 19.0);
+
+        let mut p = smoothing("method = \"percentile\"\npercentile = 0");
+        assert_eq!(feed(&mut p, &[5.0, 3.0]), vec![5.0, 3.0]);
+    }
+}
@@ -0,0 +1,594 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use super::{CommandError, Format};
+
+/// Data read natively from `/proc` and `/sys`, no external scripts needed.
+///
+/// ```toml
+/// source = "cpu"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// Busy percentage across all cores, from successive `/proc/stat` samples.
+    Cpu,
+    /// Used memory in bytes, out of the total.
+    Memory,
+    /// Used swap in bytes, out of the total.
+    Swap,
+    /// One minute load average, out of the number of cores.
+    Load,
+    /// Seconds since boot.
+    Uptime,
+    /// Charge percentage of the first battery.
+    Battery,
+    /// Bytes per second through `interface`, or all but loopback.
+    Network,
+    /// Used space in bytes of the filesystem at `path`, out of its size.
+    Disk,
+    /// Temperature in °C or fan speed in RPM from `/sys/class/hwmon`.
+    Hwmon,
+}
+
+/// Options for the sources that need them, set next to `source`:
+///
+/// ```toml
+/// source    = "network"
+/// interface = "eth0"
+/// direction = "rx"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SourceOptions {
+    pub interface: Option<String>,
+    #[serde(default)]
+    pub direction: Direction,
+    pub path: Option<String>,
+    pub chip: Option<String>,
+    pub sensor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Rx,
+    Tx,
+    #[default]
+    Both,
+}
+
+/// A reading taken from a built-in source.
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub value: f64,
+    pub max: Option<f64>,
+    /// Human readable form, used by indicators.
+    pub text: String,
+}
+
+pub type Sampler = Box<dyn FnMut() -> Result<Sample, CommandError> + Send>;
+
+impl Source {
+    /// How readings are formatted unless the widget says otherwise.
+    pub fn format(self) -> Option<Format> {
+        match self {
+            Source::Memory | Source::Swap | Source::Network | Source::Disk => Some(Format::Bytes),
+            Source::Uptime => Some(Format::Duration),
+            Source::Cpu | Source::Load | Source::Battery | Source::Hwmon => None,
+        }
+    }
+
+    /// A sampler for the source, holding on to whatever it needs
+    /// between readings.
+    pub fn sampler(self, options: &SourceOptions) -> Sampler {
+        match self {
+            Source::Cpu => {
+                // Without a baseline the first reading is the average since boot
+                let mut last = cpu_times().ok();
+                Box::new(move || cpu(&mut last))
+            }
+            Source::Memory => Box::new(|| memory("Mem")),
+            Source::Swap => Box::new(|| memory("Swap")),
+            Source::Load => Box::new(load),
+            Source::Uptime => Box::new(uptime),
+            Source::Battery => Box::new(battery),
+            Source::Network => {
+                let options = options.clone();
+                let mut last = None;
+                let mut peak = 0.0;
+                Box::new(move || network(&options, &mut last, &mut peak))
+            }
+            Source::Disk => {
+                let path = options.path.clone().unwrap_or_else(|| "/".to_string());
+                Box::new(move || disk(&path))
+            }
+            Source::Hwmon => {
+                let options = options.clone();
+                Box::new(move || hwmon(options.chip.as_deref(), options.sensor.as_deref()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Source::Cpu => "cpu",
+            Source::Memory => "memory",
+            Source::Swap => "swap",
+            Source::Load => "load",
+            Source::Uptime => "uptime",
+            Source::Battery => "battery",
+            Source::Network => "network",
+            Source::Disk => "disk",
+            Source::Hwmon => "hwmon",
+        };
+        write!(f, "source {}", name)
+    }
+}
+
+//-------------------------------------------------------------------------------------
+// Sources
+//-------------------------------------------------------------------------------------
+
+/// (busy, total) jiffies from the aggregate cpu line.
+fn cpu_times() -> Result<(u64, u64), CommandError> {
+    let stat = read("/proc/stat")?;
+    let fields = stat
+        .lines()
+        .next()
+        .filter(|l| l.starts_with("cpu "))
+        .ok_or_else(|| CommandError::Source("no cpu line in /proc/stat".to_string()))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|n| n.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+
+    // user nice system idle iowait irq softirq steal, guest time is
+    // already counted in user and nice
+    let total = fields.iter().take(8).sum::<u64>();
+    let idle = fields.iter().skip(3).take(2).sum::<u64>();
+
+    Ok((total - idle, total))
+}
+
+fn cpu(last: &mut Option<(u64, u64)>) -> Result<Sample, CommandError> {
+    let (busy, total) = cpu_times()?;
+    let (last_busy, last_total) = last.replace((busy, total)).unwrap_or((0, 0));
+
+    let total = total.saturating_sub(last_total);
+    let percent = match total {
+        0 => 0.0,
+        total => busy.saturating_sub(last_busy) as f64 / total as f64 * 100.0,
+    };
+
+    Ok(Sample {
+        value: percent,
+        max: Some(100.0),
+        text: format!("{:.0}%", percent),
+    })
+}
+
+fn memory(prefix: &str) -> Result<Sample, CommandError> {
+    let meminfo = read("/proc/meminfo")?;
+
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<f64>().ok())
+            .ok_or_else(|| CommandError::Source(format!("no {} in /proc/meminfo", name)))
+    };
+
+    let total = field(&format!("{}Total", prefix))?;
+    let free = match prefix {
+        "Mem" => field("MemAvailable")?,
+        _ => field(&format!("{}Free", prefix))?,
+    };
+
+    let (used, total) = ((total - free) * 1024.0, total * 1024.0);
+
+    Ok(Sample {
+        value: used,
+        max: Some(total),
+        text: format!("{}/{}", bytes(used), bytes(total)),
+    })
+}
+
+fn load() -> Result<Sample, CommandError> {
+    let loadavg = read("/proc/loadavg")?;
+    let averages = loadavg.split_whitespace().take(3).collect::<Vec<_>>();
+
+    let value = averages
+        .first()
+        .and_then(|l| l.parse::<f64>().ok())
+        .ok_or_else(|| CommandError::Parse(loadavg.clone()))?;
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+
+    Ok(Sample {
+        value,
+        max: Some(cores as f64),
+        text: averages.join(" "),
+    })
+}
+
+fn uptime() -> Result<Sample, CommandError> {
+    let uptime = read("/proc/uptime")?;
+    let seconds = uptime
+        .split_whitespace()
+        .next()
+        .and_then(|s| s.parse::<f64>().ok())
+        .ok_or_else(|| CommandError::Parse(uptime.clone()))?;
+
+    let minutes = seconds as u64 / 60;
+    let text = match (minutes / 1440, minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{}m", m),
+        (0, h, m) => format!("{}h {}m", h, m),
+        (d, h, m) => format!("{}d {}h {}m", d, h, m),
+    };
+
+    Ok(Sample {
+        value: seconds,
+        max: None,
+        text,
+    })
+}
+
+fn battery() -> Result<Sample, CommandError> {
+    let supplies = fs::read_dir("/sys/class/power_supply")
+        .map_err(|e| CommandError::Source(format!("/sys/class/power_supply: {}", e.kind())))?;
+
+    let battery = supplies
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| read(path.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+        .ok_or_else(|| CommandError::Source("no battery found".to_string()))?;
+
+    let capacity = read(battery.join("capacity"))?;
+    let value = capacity
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| CommandError::Parse(capacity.trim().to_string()))?;
+    let status = read(battery.join("status")).unwrap_or_default();
+
+    Ok(Sample {
+        value,
+        max: Some(100.0),
+        text: format!("{:.0}% {}", value, status.trim()),
+    })
+}
+
+/// (rx, tx) byte counters for `interface`, or summed over all but loopback.
+fn net_bytes(interface: Option<&str>) -> Result<(u64, u64), CommandError> {
+    let dev = read("/proc/net/dev")?;
+
+    let counters = dev
+        .lines()
+        .skip(2)
+        .filter_map(|l| l.split_once(':'))
+        .map(|(name, counters)| (name.trim(), counters))
+        .filter(|(name, _)| match interface {
+            Some(interface) => *name == interface,
+            None => *name != "lo",
+        })
+        .map(|(_, counters)| {
+            let fields = counters
+                .split_whitespace()
+                .map(|n| n.parse::<u64>().unwrap_or(0))
+                .collect::<Vec<_>>();
+            (
+                fields.first().copied().unwrap_or(0),
+                fields.get(8).copied().unwrap_or(0),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if counters.is_empty() {
+        let interface = interface.unwrap_or("any interface");
+        return Err(CommandError::Source(format!(
+            "no {} in /proc/net/dev",
+            interface
+        )));
+    }
+
+    Ok(counters
+        .into_iter()
+        .fold((0, 0), |(rx, tx), (r, t)| (rx + r, tx + t)))
+}
+
+fn network(
+    options: &SourceOptions,
+    last: &mut Option<(Instant, u64, u64)>,
+    peak: &mut f64,
+) -> Result<Sample, CommandError> {
+    let (rx, tx) = net_bytes(options.interface.as_deref())?;
+    let now = Instant::now();
+
+    // Counters going backwards (interface reset) count as no traffic
+    let (rx_rate, tx_rate) = match last.replace((now, rx, tx)) {
+        Some((then, last_rx, last_tx)) => {
+            let elapsed = now.duration_since(then).as_secs_f64().max(f64::EPSILON);
+            (
+                rx.saturating_sub(last_rx) as f64 / elapsed,
+                tx.saturating_sub(last_tx) as f64 / elapsed,
+            )
+        }
+        None => (0.0, 0.0),
+    };
+
+    let (value, text) = match options.direction {
+        Direction::Rx => (rx_rate, format!("↓{}/s", bytes(rx_rate))),
+        Direction::Tx => (tx_rate, format!("↑{}/s", bytes(tx_rate))),
+        Direction::Both => (
+            rx_rate + tx_rate,
+            format!("↓{}/s ↑{}/s", bytes(rx_rate), bytes(tx_rate)),
+        ),
+    };
+
+    // Scale the meter to the busiest the link has been, rounded up to
+    // a 1, 2 or 5 step so the max doesn't jitter.
+    *peak = peak.max(value);
+
+    Ok(Sample {
+        value,
+        max: Some(nice_ceil(peak.max(1024.0))),
+        text,
+    })
+}
+
+/// (size, used, available) bytes of the filesystem holding `path`.
+#[cfg(unix)]
+fn statvfs(path: &str) -> Result<(u64, u64, u64), CommandError> {
+    use std::ffi::CString;
+
+    let c_path =
+        CString::new(path).map_err(|_| CommandError::Source(format!("bad path {:?}", path)))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: `c_path` is a valid nul terminated string and `stat` is only
+    // read after statvfs reports success.
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            let err = std::io::Error::last_os_error();
+            return Err(CommandError::Source(format!("{}: {}", path, err.kind())));
+        }
+        stat.assume_init()
+    };
+
+    let block = stat.f_frsize as u64;
+    let size = stat.f_blocks as u64 * block;
+    let free = stat.f_bfree as u64 * block;
+    let available = stat.f_bavail as u64 * block;
+
+    Ok((size, size - free, available))
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &str) -> Result<(u64, u64, u64), CommandError> {
+    Err(CommandError::Source(
+        "disk source needs a unix system".to_string(),
+    ))
+}
+
+fn disk(path: &str) -> Result<Sample, CommandError> {
+    let (size, used, available) = statvfs(path)?;
+    Ok(Sample {
+        value: used as f64,
+        max: Some(size as f64),
+        text: format!(
+            "{}/{} ({} free)",
+            bytes(used as f64),
+            bytes(size as f64),
+            bytes(available as f64)
+        ),
+    })
+}
+
+/// Mount points of real, block device backed, filesystems.
+pub fn mounts() -> Vec<String> {
+    parse_mounts(&fs::read_to_string("/proc/mounts").unwrap_or_default())
+}
+
+/// Mount points in the contents of /proc/mounts, see `mounts`.
+fn parse_mounts(mounts: &str) -> Vec<String> {
+    let mut devices = Vec::new();
+
+    mounts
+        .lines()
+        .filter_map(|l| {
+            let mut fields = l.split_whitespace();
+            Some((fields.next()?, fields.next()?, fields.next()?))
+        })
+        .filter(|(device, _, fs)| device.starts_with('/') && *fs != "squashfs")
+        // Bind mounts show up once per mount point, keep the first
+        .filter(|(device, _, _)| {
+            let seen = devices.contains(device);
+            devices.push(*device);
+            !seen
+        })
+        .map(|(_, mount_point, _)| unescape(mount_point))
+        .collect()
+}
+
+/// /proc/mounts escapes spaces and friends as octal, `\040`.
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut rest = field;
+
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        match rest
+            .get(i + 1..i + 4)
+            .and_then(|o| u8::from_str_radix(o, 8).ok())
+        {
+            Some(c) => {
+                out.push(c as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Find the `temp*_input` or `fan*_input` file on the first chip named
+/// `chip` whose label, or file name like `temp1`, is `sensor`. Without a
+/// `sensor` the first temperature wins, without a `chip` any chip goes.
+fn hwmon_input(chip: Option<&str>, sensor: Option<&str>) -> Result<PathBuf, CommandError> {
+    let hwmon = Path::new("/sys/class/hwmon");
+    let mut chips = fs::read_dir(hwmon)
+        .map_err(|e| CommandError::Source(format!("{}: {}", hwmon.display(), e.kind())))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    chips.sort();
+
+    for dir in chips {
+        let name = read(dir.join("name")).unwrap_or_default();
+        if chip.is_some_and(|chip| chip != name.trim()) {
+            continue;
+        }
+
+        let mut inputs = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file = entry.file_name().into_string().ok()?;
+                let kind = file.strip_suffix("_input")?;
+                (kind.starts_with("temp") || kind.starts_with("fan")).then(|| kind.to_string())
+            })
+            .collect::<Vec<_>>();
+        // temp before fan, and temp2 before temp10
+        inputs.sort_by_key(|kind| (!kind.starts_with("temp"), kind.len(), kind.clone()));
+
+        let found = inputs.into_iter().find(|kind| match sensor {
+            None => kind.starts_with("temp"),
+            Some(sensor) => {
+                sensor == kind
+                    || read(dir.join(format!("{}_label", kind))).is_ok_and(|l| l.trim() == sensor)
+            }
+        });
+
+        if let Some(kind) = found {
+            return Ok(dir.join(format!("{}_input", kind)));
+        }
+    }
+
+    Err(CommandError::Source(format!(
+        "no hwmon sensor {} on {}",
+        sensor.unwrap_or("temp*"),
+        chip.unwrap_or("any chip")
+    )))
+}
+
+fn hwmon(chip: Option<&str>, sensor: Option<&str>) -> Result<Sample, CommandError> {
+    let input = hwmon_input(chip, sensor)?;
+    let kind = input
+        .file_name()
+        .and_then(|f| f.to_str())
+        .and_then(|f| f.strip_suffix("_input"))
+        .unwrap_or_default()
+        .to_string();
+
+    let number = |file: &str| -> Option<f64> {
+        read(input.with_file_name(format!("{}_{}", kind, file)))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+
+    let raw = read(&input)?;
+    let raw = raw
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| CommandError::Parse(raw.trim().to_string()))?;
+
+    // Temperatures are in millidegrees, fans in plain RPM
+    let (value, max, text) = if kind.starts_with("temp") {
+        let max = number("crit").or_else(|| number("max")).map(|m| m / 1000.0);
+        (raw / 1000.0, max, format!("{:.1}°C", raw / 1000.0))
+    } else {
+        (raw, number("max"), format!("{:.0} RPM", raw))
+    };
+
+    Ok(Sample { value, max, text })
+}
+
+/// Round up to the next 1, 2 or 5 times a power of ten.
+fn nice_ceil(n: f64) -> f64 {
+    let magnitude = 10_f64.powf(n.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|step| step * magnitude)
+        .find(|&step| step >= n)
+        .unwrap_or(n)
+}
+
+fn bytes(bytes: f64) -> String {
+    Format::Bytes.apply(bytes, None)
+}
+
+fn read(path: impl AsRef<Path>) -> Result<String, CommandError> {
+    let path = path.as_ref();
+    fs::read_to_string(path)
+        .map_err(|e| CommandError::Source(format!("{}: {}", path.display(), e.kind())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nice_ceil_rounds_up_to_1_2_or_5() {
+        assert_eq!(nice_ceil(1.0), 1.0);
+        assert_eq!(nice_ceil(1.5), 2.0);
+        assert_eq!(nice_ceil(3.0), 5.0);
+        assert_eq!(nice_ceil(7.0), 10.0);
+        assert_eq!(nice_ceil(20.0), 20.0);
+        assert_eq!(nice_ceil(1200.0), 2000.0);
+        assert_eq!(nice_ceil(0.3), 0.5);
+    }
+
+    #[test]
+    fn unescape_octal() {
+        assert_eq!(unescape("/media/my\\040disk"), "/media/my disk");
+        assert_eq!(unescape("\\011tab"), "\ttab");
+        assert_eq!(unescape("plain"), "plain");
+    }
+
+    #[test]
+    fn unescape_keeps_malformed_escapes() {
+        assert_eq!(unescape("a\\b"), "a\\b");
+        assert_eq!(unescape("a\\04"), "a\\04");
+        assert_eq!(unescape("a\\"), "a\\");
+    }
+
+    #[test]
+    fn mounts_of_block_devices_once_each() {
+        let mounts = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid 0 0
+/dev/nvme0n1p1 /boot/efi vfat rw 0 0
+/dev/loop0 /snap/core/1 squashfs ro 0 0
+/dev/nvme0n1p2 /var/lib/docker ext4 rw 0 0
+/dev/sda1 /media/my\\040disk ext4 rw 0 0
+broken
+";
+        assert_eq!(
+            parse_mounts(mounts),
+            vec!["/", "/boot/efi", "/media/my disk"]
+        );
+    }
+}
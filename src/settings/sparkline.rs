@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tinybit::{widgets::Text, ScreenPos, Viewport};
+
+use crate::layout::Rect;
+use crate::popup::Popup;
+use crate::scheduler::Scheduler;
+
+use super::colors::Colors;
+use super::header::Header;
+use super::inline::{self, Inline};
+use super::{Probe, Widget};
+
+/// Block glyphs from lowest to highest.
+const GLYPHS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Recent history of a value, one block glyph per sample.
+#[derive(Debug, Deserialize)]
+pub struct Sparkline {
+    title: Option<String>,
+    unit: Option<String>,
+
+    #[serde(flatten)]
+    probe: Probe,
+    /// Samples to keep, the width of the column if left out.
+    history: Option<usize>,
+
+    #[serde(default)]
+    pub right: bool,
+    #[serde(default)]
+    pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+
+    #[serde(default)]
+    pub theme: Colors,
+
+    #[serde(skip_deserializing)]
+    samples: VecDeque<f64>,
+    /// Cells the line was last drawn in, unknown until first shown.
+    #[serde(skip_deserializing)]
+    width: Option<usize>,
+    #[serde(skip_deserializing)]
+    selected: bool,
+}
+
+impl Sparkline {
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        self.probe.init(scheduler)
+    }
+
+    /// Lowest and highest sample kept.
+    fn bounds(&self) -> Option<(f64, f64)> {
+        let min = self.samples.iter().copied().reduce(f64::min)?;
+        let max = self.samples.iter().copied().reduce(f64::max)?;
+        Some((min, max))
+    }
+
+    /// The samples that fit in `width`, scaled to the known range or,
+    /// without one, to the samples themselves.
+    fn line(&self, width: usize) -> String {
+        let (low, high) = match self.bounds() {
+            _ if self.probe.max_value > self.probe.min_value => {
+                (self.probe.min_value, self.probe.max_value)
+            }
+            Some(bounds) => bounds,
+            None => return String::new(),
+        };
+
+        let skip = self.samples.len().saturating_sub(width);
+        self.samples
+            .iter()
+            .skip(skip)
+            .map(|&value| {
+                let ratio = ((value - low) / (high - low)).clamp(0.0, 1.0);
+                // A flat line sits at the bottom
+                let ratio = if ratio.is_finite() { ratio } else { 0.0 };
+                GLYPHS[(ratio * (GLYPHS.len() - 1) as f64).round() as usize]
+            })
+            .collect()
+    }
+
+    /// Title with `reading` on the right, or the error in its place.
+    fn header(&self, reading: Option<String>) -> Header<'_> {
+        Header {
+            title: self.probe.label.as_deref().or(self.title.as_deref()),
+            reading,
+            error: self.probe.error(),
+            colors: self.theme.text_colors(self.probe.stale, self.selected),
+            error_color: self.theme.error_color,
+        }
+    }
+}
+
+//----------------------------------------------------------------------------+
+// Trait Impl                                                                 |
+//----------------------------------------------------------------------------+
+
+impl Widget for Sparkline {
+    fn update(&mut self) -> Result<()> {
+        if self.probe.update().is_some() {
+            self.samples.push_back(self.probe.current_value);
+        }
+
+        // Never shown yet, keep what the widest screen could show
+        let width = self.width.unwrap_or(u16::MAX as usize);
+        let history = self.history.unwrap_or(width).max(1);
+        while self.samples.len() > history {
+            self.samples.pop_front();
+        }
+
+        Ok(())
+    }
+
+    fn update_and_draw(
+        &mut self,
+        viewport: &mut Viewport,
+        area: Rect,
+        _resized: &bool,
+    ) -> Result<()> {
+        let pos = area.pos();
+        let width = area.width as usize;
+        self.width = Some(width);
+        self.update()?;
+
+        let fg_color = self.theme.fg(self.probe.stale);
+        let labels = self.bounds().map(|(min, max)| {
+            format!(
+                "min {} max {}{}",
+                self.probe.number(min),
+                self.probe.number(max),
+                self.unit.as_deref().unwrap_or("")
+            )
+        });
+        self.header(labels).draw(viewport, area);
+
+        // Pad on the left so the newest sample is always at the right edge
+        let line = self.line(width);
+        let padding = width.saturating_sub(line.chars().count());
+        viewport.draw_widget(
+            &Text::new(
+                format!("{}{}", " ".repeat(padding), line),
+                fg_color,
+                self.theme.bg_color,
+            ),
+            ScreenPos::new(pos.x, pos.y + 1),
+        );
+
+        Ok(())
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.bottom
+    }
+
+    fn is_right(&self) -> bool {
+        self.right
+    }
+
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    fn vertical_size(&self) -> u8 {
+        2
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        self.width = Some(inline::BAR as usize);
+        self.update()?;
+
+        let fg_color = self.theme.fg(self.probe.stale);
+        let last = self.samples.back().map(|&value| {
+            format!(
+                "{}{}",
+                self.probe.number(value),
+                self.unit.as_deref().unwrap_or("")
+            )
+        });
+        let header = self.header(last);
+        let mut line = Inline::new(pos, width);
+
+        header.draw_title(viewport, &mut line);
+        line.text(
+            viewport,
+            &self.line(inline::BAR as usize),
+            (fg_color, self.theme.bg_color),
+        );
+        header.draw_reading(viewport, &mut line);
+
+        Ok(line.used())
+    }
+
+    fn details(&self) -> Option<Popup> {
+        Some(Popup::new(
+            self.title.as_deref().unwrap_or("Sparkline").trim(),
+            self.probe.details(),
+        ))
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::Result;
+use serde::Deserialize;
+use tinybit::{widgets::Text, Color, ScreenPos, Viewport};
+
+use crate::layout::Rect;
+use crate::popup::Popup;
+use crate::scheduler::Scheduler;
+use crate::MeterTheme;
+
+use super::header::Header;
+use super::inline::{self, Inline};
+use super::{parse_ansi, Probe, Widget, PALETTE};
+
+/// One of the values in a stacked meter.
+#[derive(Debug, Deserialize)]
+pub struct Segment {
+    title: Option<String>,
+    /// ANSI color, picked from a palette if left out.
+    color: Option<u8>,
+
+    #[serde(skip_deserializing)]
+    fg_color: Option<Color>,
+}
+
+/// A meter split into segments, one per value the command prints,
+/// like used/buffers/cache of memory.
+#[derive(Debug, Deserialize)]
+pub struct Stack {
+    title: Option<String>,
+    unit: Option<String>,
+    prefix: Option<String>,
+
+    #[serde(flatten)]
+    probe: Probe,
+    #[serde(default)]
+    segments: Vec<Segment>,
+
+    /// Name and value of every segment on a line below the bar.
+    #[serde(default)]
+    legend: bool,
+    #[serde(default)]
+    reading: bool,
+
+    #[serde(default)]
+    pub right: bool,
+    #[serde(default)]
+    pub bottom: bool,
+    /// Layout region to draw in, see `Layout`.
+    pub region: Option<String>,
+
+    #[serde(default)]
+    pub theme: MeterTheme,
+
+    #[serde(skip_deserializing)]
+    selected: bool,
+}
+
+impl Stack {
+    pub fn init(&mut self, scheduler: &mut Scheduler) -> Result<()> {
+        for (n, segment) in self.segments.iter_mut().enumerate() {
+            segment.fg_color = parse_ansi(segment.color.or(Some(PALETTE[n % PALETTE.len()])));
+        }
+
+        self.probe.read_several();
+        self.probe.init(scheduler)
+    }
+
+    /// The max of the probe, or the total of the segments without one.
+    fn range(&self) -> (f64, f64) {
+        let probe = &self.probe;
+        if probe.max_value > probe.min_value {
+            (probe.min_value, probe.max_value)
+        } else {
+            (probe.min_value, probe.min_value + probe.current_value)
+        }
+    }
+
+    /// Segment colors, values beyond the configured segments fall back
+    /// to the palette.
+    fn color(&self, n: usize) -> Option<Color> {
+        match self.segments.get(n) {
+            Some(segment) => segment.fg_color,
+            None => parse_ansi(Some(PALETTE[n % PALETTE.len()])),
+        }
+    }
+
+    /// Share of the bar and color of every value.
+    fn segments(&self) -> Vec<(f64, Option<Color>)> {
+        let (min, max) = self.range();
+        self.probe
+            .values
+            .iter()
+            .enumerate()
+            .map(|(n, value)| {
+                let ratio = value / (max - min);
+                (if ratio.is_finite() { ratio } else { 0.0 }, self.color(n))
+            })
+            .collect()
+    }
+
+    /// Total and max, or only the total without a max.
+    fn reading(&self) -> String {
+        if self.probe.max_value > self.probe.min_value {
+            return self.probe.reading(self.unit.as_deref());
+        }
+
+        format!(
+            "{}{}",
+            self.probe.number(self.probe.current_value),
+            self.unit.as_deref().unwrap_or("")
+        )
+    }
+
+    /// The label picked out of the output, or the title without one.
+    fn title(&self) -> Option<&str> {
+        self.probe.label.as_deref().or(self.title.as_deref())
+    }
+
+    /// Whether there is a line above the bar, see `Meter::has_header`.
+    fn has_header(&self) -> bool {
+        self.reading || self.title.is_some() || self.probe.has_label()
+    }
+
+    fn header(&self) -> Header<'_> {
+        let ratio = self.probe.ratio(self.probe.current_value);
+        Header {
+            title: self.title(),
+            reading: self.reading.then(|| self.reading()),
+            error: self.probe.error(),
+            colors: self
+                .theme
+                .text_colors(ratio, self.probe.stale, self.selected),
+            error_color: self.theme.colors.error_color,
+        }
+    }
+}
+
+//----------------------------------------------------------------------------+
+// Trait Impl                                                                 |
+//----------------------------------------------------------------------------+
+
+impl Widget for Stack {
+    fn update(&mut self) -> Result<()> {
+        self.probe.update();
+        Ok(())
+    }
+
+    fn update_and_draw(
+        &mut self,
+        viewport: &mut Viewport,
+        area: Rect,
+        resized: &bool,
+    ) -> Result<()> {
+        self.update()?;
+
+        if *resized {
+            self.theme.resize(area.width.min(u8::MAX as u16) as u8)
+        };
+
+        let pos = area.pos();
+        let stale = self.probe.stale;
+
+        self.header().draw(viewport, area);
+
+        let bar_pos = ScreenPos::new(pos.x, pos.y + self.has_header() as u16);
+
+        let segments = self.segments();
+
+        // Without a title or reading the error takes the place of the bar
+        if self.probe.error().is_none() || self.has_header() {
+            self.theme.draw_segments(
+                viewport,
+                (self.prefix.as_deref(), stale, self.selected),
+                &segments,
+                bar_pos,
+            );
+        }
+
+        if self.legend {
+            let mut x = pos.x;
+            for (n, &value) in self.probe.values.iter().enumerate() {
+                let name = self
+                    .segments
+                    .get(n)
+                    .and_then(|s| s.title.clone())
+                    .unwrap_or_else(|| (n + 1).to_string());
+                let entry = format!("■ {} {}", name, self.probe.number(value));
+                let color = if stale {
+                    self.theme.colors.fg(true)
+                } else {
+                    self.color(n)
+                };
+
+                viewport.draw_widget(
+                    &Text::new(&entry, color, None),
+                    ScreenPos::new(x, bar_pos.y + 1),
+                );
+                x += entry.chars().count() as u16 + 2;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.bottom
+    }
+
+    fn is_right(&self) -> bool {
+        self.right
+    }
+
+    fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    fn vertical_size(&self) -> u8 {
+        1 + self.has_header() as u8 + self.legend as u8
+    }
+
+    fn draw_inline(&mut self, viewport: &mut Viewport, pos: ScreenPos, width: u16) -> Result<u16> {
+        self.update()?;
+
+        self.theme.fit(self.prefix.as_deref(), inline::BAR);
+
+        let header = self.header();
+        let mut line = Inline::new(pos, width);
+        header.draw_title(viewport, &mut line);
+
+        if let Some(bar_pos) = line.reserve(self.theme.width() as u16) {
+            let segments = self.segments();
+            self.theme.draw_segments(
+                viewport,
+                (self.prefix.as_deref(), self.probe.stale, self.selected),
+                &segments,
+                bar_pos,
+            );
+        }
+
+        header.draw_reading(viewport, &mut line);
+
+        Ok(line.used())
+    }
+
+    fn details(&self) -> Option<Popup> {
+        let title = self
+            .title
+            .as_deref()
+            .or(self.prefix.as_deref())
+            .unwrap_or("Stack");
+
+        Some(Popup::new(title.trim(), self.probe.details()))
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+}